@@ -0,0 +1,137 @@
+//! Snapshots the editor state regularly, so it can be restored after a crash.
+//!
+//! The snapshot is a [`Project`] in the state directory which is removed whenever the editor is
+//! closed normally. It's named after the process, so a snapshot whose process isn't running
+//! anymore means that flakeshot didn't exit cleanly.
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Context;
+
+use crate::{
+    get_xdg,
+    project::{self, Project},
+};
+
+/// How often the editor state is stored (if it changed).
+pub const INTERVAL: Duration = Duration::from_secs(10);
+
+/// The snapshots are called `autosave-<pid>.flakeshot`.
+const AUTOSAVE_PREFIX: &str = "autosave-";
+
+/// The snapshot of the crashed session is moved here, so the new session doesn't overwrite it.
+const RECOVERED_FILENAME: &str = "recovered.flakeshot";
+
+/// Increased whenever the snapshot is discarded, so snapshots which are still being written
+/// by an older session don't bring it back.
+static SESSION: AtomicU64 = AtomicU64::new(0);
+
+/// Serializes writing and discarding the snapshot.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Returns the current session which has to be passed to [`save`].
+pub fn session() -> u64 {
+    SESSION.load(Ordering::SeqCst)
+}
+
+/// Stores the project as snapshot of the session unless the session has been discarded in the
+/// meantime.
+pub fn save(project: &Project, session: u64) -> anyhow::Result<()> {
+    let _lock = LOCK.lock().unwrap();
+    if SESSION.load(Ordering::SeqCst) != session {
+        return Ok(());
+    }
+
+    let path = get_xdg()
+        .place_state_file(autosave_filename(std::process::id()))
+        .context("Couldn't access the autosave")?;
+
+    // a crash while writing mustn't destroy the previous snapshot
+    let temp_path = path.with_extension("tmp");
+    project.save(&temp_path)?;
+    std::fs::rename(&temp_path, &path).context("Couldn't replace the autosave")
+}
+
+/// Removes the snapshot since the session ended normally.
+pub fn discard() {
+    let _lock = LOCK.lock().unwrap();
+    SESSION.fetch_add(1, Ordering::SeqCst);
+
+    if let Some(path) = get_xdg().find_state_file(autosave_filename(std::process::id())) {
+        if let Err(err) = std::fs::remove_file(&path) {
+            tracing::warn!("Couldn't remove {}: {}", path.to_string_lossy(), err);
+        }
+    }
+}
+
+/// Returns the snapshot of a session which didn't end normally, if there's any.
+///
+/// The snapshot is moved out of the way first, so it isn't offered by another start. It has to
+/// be removed by [`remove_recovered`] once it's restored or dismissed.
+pub fn take_recovered() -> Option<PathBuf> {
+    let _lock = LOCK.lock().unwrap();
+
+    let path = std::fs::read_dir(get_xdg().get_state_home())
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| owner(path).is_some_and(|pid| !is_running(pid)))?;
+    let recovered = get_xdg().place_state_file(RECOVERED_FILENAME).ok()?;
+
+    match std::fs::rename(&path, &recovered) {
+        Ok(()) => Some(recovered),
+        Err(err) => {
+            tracing::warn!("Couldn't move {}: {}", path.to_string_lossy(), err);
+            None
+        }
+    }
+}
+
+/// Whether the path is the snapshot of [`take_recovered`].
+pub fn is_recovered(path: &Path) -> bool {
+    get_xdg().find_state_file(RECOVERED_FILENAME).as_deref() == Some(path)
+}
+
+/// Removes the snapshot of [`take_recovered`].
+pub fn remove_recovered(path: &Path) {
+    if let Err(err) = std::fs::remove_file(path) {
+        tracing::warn!("Couldn't remove {}: {}", path.to_string_lossy(), err);
+    }
+}
+
+fn autosave_filename(pid: u32) -> String {
+    format!("{}{}.{}", AUTOSAVE_PREFIX, pid, project::EXTENSION)
+}
+
+/// Returns the id of the process which wrote the snapshot.
+fn owner(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(AUTOSAVE_PREFIX)?
+        .strip_suffix(project::EXTENSION)?
+        .strip_suffix('.')?
+        .parse()
+        .ok()
+}
+
+fn is_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owner_of_autosave() {
+        assert_eq!(owner(Path::new(&autosave_filename(42))), Some(42));
+        assert_eq!(owner(Path::new("autosave-.flakeshot")), None);
+        assert_eq!(owner(Path::new("recovered.flakeshot")), None);
+    }
+}
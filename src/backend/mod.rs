@@ -1,4 +1,8 @@
 //! Contains the different backends to get the screenshot from.
+use std::{str::FromStr, time::Duration};
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
 
 pub mod wayland;
 pub mod x11;
@@ -14,6 +18,19 @@ pub enum Error {
     /// Represents that an error occured while trying to get a screenshot on Wayland.
     #[error(transparent)]
     Wayland(#[from] wayland::wayland_error::WaylandError),
+
+    /// Represents that the requested output (see [`CaptureTarget::Output`]) doesn't exist.
+    #[error("There's no output with the name '{0}'.")]
+    UnknownOutput(String),
+
+    /// Represents that the requested region (see [`CaptureTarget::Region`]) doesn't overlap
+    /// with any output.
+    #[error("The region doesn't overlap with any output.")]
+    EmptyRegion,
+
+    /// Represents that the requested feature can't be provided on wayland.
+    #[error("{0} isn't supported on wayland.")]
+    UnsupportedOnWayland(&'static str),
 }
 
 /// An alias type for better code readability.
@@ -29,6 +46,16 @@ pub enum MonitorInfo {
     Wayland { name: String, description: String },
 }
 
+impl MonitorInfo {
+    /// Returns the name of the output (for example `DP-1`).
+    pub fn name(&self) -> &str {
+        match self {
+            Self::X11 { name } => name,
+            Self::Wayland { name, .. } => name,
+        }
+    }
+}
+
 /// Some general information about an output.
 #[derive(Debug, Clone)]
 pub struct OutputInfo {
@@ -51,6 +78,97 @@ pub struct OutputInfo {
     pub monitor_info: MonitorInfo,
 }
 
+/// A rectangular area of the (combined) screen in global coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    /// The x-value of the top-left corner of the region.
+    pub x: i32,
+
+    /// The y-value of the top-left corner of the region.
+    pub y: i32,
+
+    /// The width of the region.
+    pub width: u32,
+
+    /// The height of the region.
+    pub height: u32,
+}
+
+/// Parses the format of [slurp](https://github.com/emersion/slurp): `<x>,<y> <width>x<height>`
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "'{}' doesn't match the format '<x>,<y> <width>x<height>'",
+                s
+            )
+        };
+
+        let (position, size) = s.trim().split_once(' ').ok_or_else(invalid)?;
+        let (x, y) = position.split_once(',').ok_or_else(invalid)?;
+        let (width, height) = size.trim().split_once('x').ok_or_else(invalid)?;
+
+        Ok(Self {
+            x: x.parse().map_err(|_| invalid())?,
+            y: y.parse().map_err(|_| invalid())?,
+            width: width.parse().map_err(|_| invalid())?,
+            height: height.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Describes what [`capture`] should take a screenshot of.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureTarget {
+    /// All outputs combined into one image.
+    #[default]
+    Screen,
+
+    /// Only the output with the given name.
+    Output(String),
+
+    /// A region of the combined image of all outputs.
+    Region(Region),
+
+    /// The window which currently has the focus. (X11 only)
+    ActiveWindow,
+}
+
+/// Grabs repeated frames of a [`Region`] from the current display server.
+///
+/// In contrast to [`create_screenshots`], the connection to the display server is kept
+/// alive between the frames which makes it suitable for recordings.
+pub enum FrameGrabber {
+    X11(x11::X11FrameGrabber),
+    Wayland(wayland::wayland_frame_grabber::WaylandFrameGrabber),
+}
+
+impl FrameGrabber {
+    pub fn new(region: Region) -> Result<Self, Error> {
+        if is_wayland() {
+            Ok(Self::Wayland(
+                wayland::wayland_frame_grabber::WaylandFrameGrabber::new(region)?,
+            ))
+        } else {
+            Ok(Self::X11(x11::X11FrameGrabber::new(region)?))
+        }
+    }
+
+    /// Returns the next frame of the region.
+    ///
+    /// # Return value
+    /// `None` if the content of the region didn't change within the given `timeout`.
+    /// Backends which can't detect changes (X11) will always return a frame.
+    pub fn next_frame(&mut self, timeout: Duration) -> Result<Option<RgbaImage>, Error> {
+        match self {
+            Self::X11(grabber) => grabber.next_frame().map(Some).map_err(Error::from),
+            Self::Wayland(grabber) => grabber.next_frame(timeout).map_err(Error::from),
+        }
+    }
+}
+
 /// Checks if system is using Wayland
 pub fn is_wayland() -> bool {
     wayland_client::Connection::connect_to_env().is_ok()
@@ -72,3 +190,180 @@ pub fn create_screenshots() -> Result<Vec<(OutputInfo, image::DynamicImage)>, Er
         x11::create_screenshots().map_err(Error::from)
     }
 }
+
+/// Returns the names of all outputs (for example `DP-1`) without taking any screenshots.
+pub fn get_output_names() -> Result<Vec<String>, Error> {
+    if is_wayland() {
+        wayland::get_output_names().map_err(Error::from)
+    } else {
+        x11::get_output_names().map_err(Error::from)
+    }
+}
+
+/// Creates a single (opaque) image of the given target.
+///
+/// In contrast to [`create_screenshots`], the screenshots of the outputs are already
+/// combined according to their position.
+pub fn capture(target: &CaptureTarget) -> Result<RgbaImage, Error> {
+    let screenshots = create_screenshots()?;
+
+    let mut image = match target {
+        CaptureTarget::Output(name) => screenshots
+            .into_iter()
+            .find(|(output_info, _)| output_info.monitor_info.name() == name)
+            .map(|(_, image)| image.to_rgba8())
+            .ok_or_else(|| Error::UnknownOutput(name.clone()))?,
+        CaptureTarget::Screen => combine_screenshots(&screenshots).1,
+        CaptureTarget::Region(region) => crop_region(&screenshots, region)?,
+        CaptureTarget::ActiveWindow => crop_region(&screenshots, &get_active_window_region()?)?,
+    };
+
+    // not every backend guarantees a meaningful alpha channel
+    for pixel in image.pixels_mut() {
+        pixel.0[3] = u8::MAX;
+    }
+
+    Ok(image)
+}
+
+/// Returns the geometry of the window which currently has the focus.
+pub fn get_active_window_region() -> Result<Region, Error> {
+    if is_wayland() {
+        // wayland doesn't expose the geometry of other windows to clients
+        Err(Error::UnsupportedOnWayland("Capturing the active window"))
+    } else {
+        x11::get_active_window_region().map_err(Error::from)
+    }
+}
+
+/// Cuts the region out of the combined screenshots.
+fn crop_region(
+    screenshots: &[(OutputInfo, image::DynamicImage)],
+    region: &Region,
+) -> Result<RgbaImage, Error> {
+    let (origin, combined) = combine_screenshots(screenshots);
+
+    let (x, y, width, height) =
+        intersect(region, origin, combined.dimensions()).ok_or(Error::EmptyRegion)?;
+
+    Ok(image::imageops::crop_imm(&combined, x, y, width, height).to_image())
+}
+
+/// Returns the part of the region which lies within the image whose top-left corner is at
+/// `origin`. The result is relative to the image: `(x, y, width, height)`.
+fn intersect(
+    region: &Region,
+    (min_x, min_y): (i64, i64),
+    (image_width, image_height): (u32, u32),
+) -> Option<(u32, u32, u32, u32)> {
+    let left = i64::from(region.x) - min_x;
+    let top = i64::from(region.y) - min_y;
+    let right = (left + i64::from(region.width)).min(i64::from(image_width));
+    let bottom = (top + i64::from(region.height)).min(i64::from(image_height));
+
+    // the part which lies outside of the image is cut off
+    let x = left.max(0);
+    let y = top.max(0);
+
+    if right <= x || bottom <= y {
+        return None;
+    }
+
+    Some((x as u32, y as u32, (right - x) as u32, (bottom - y) as u32))
+}
+
+/// Places each screenshot according to the position of its output.
+///
+/// # Return value
+/// The global coordinates of the top-left corner and the combined image.
+fn combine_screenshots(
+    screenshots: &[(OutputInfo, image::DynamicImage)],
+) -> ((i64, i64), RgbaImage) {
+    let min_x = screenshots
+        .iter()
+        .map(|(output_info, _)| i64::from(output_info.x))
+        .min()
+        .unwrap_or(0);
+    let min_y = screenshots
+        .iter()
+        .map(|(output_info, _)| i64::from(output_info.y))
+        .min()
+        .unwrap_or(0);
+
+    let (width, height) = screenshots
+        .iter()
+        .map(|(output_info, image)| {
+            (
+                i64::from(output_info.x) - min_x + i64::from(image.width()),
+                i64::from(output_info.y) - min_y + i64::from(image.height()),
+            )
+        })
+        .fold((0, 0), |(width, height), (right, bottom)| {
+            (width.max(right), height.max(bottom))
+        });
+
+    let mut combined = RgbaImage::new(width as u32, height as u32);
+    for (output_info, image) in screenshots {
+        image::imageops::replace(
+            &mut combined,
+            &image.to_rgba8(),
+            i64::from(output_info.x) - min_x,
+            i64::from(output_info.y) - min_y,
+        );
+    }
+
+    ((min_x, min_y), combined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{intersect, Region};
+
+    #[test]
+    fn test_region_from_slurp_format() {
+        assert_eq!(
+            "10,-20 300x400\n".parse::<Region>(),
+            Ok(Region {
+                x: 10,
+                y: -20,
+                width: 300,
+                height: 400
+            })
+        );
+
+        assert!("10,20".parse::<Region>().is_err());
+        assert!("10,20 -300x400".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_intersect_cuts_off_overhang() {
+        let region = |x, y, width, height| Region {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        // inside of the image
+        assert_eq!(
+            intersect(&region(10, 20, 30, 40), (0, 0), (100, 100)),
+            Some((10, 20, 30, 40))
+        );
+
+        // overhang at the top-left: the width and height shrink as well
+        assert_eq!(
+            intersect(&region(-10, -5, 30, 40), (0, 0), (100, 100)),
+            Some((0, 0, 20, 35))
+        );
+
+        // overhang at the bottom-right with an output left of the origin
+        assert_eq!(
+            intersect(&region(-1900, 80, 50, 50), (-1920, 0), (3840, 100)),
+            Some((20, 80, 50, 20))
+        );
+
+        // completely outside
+        assert_eq!(intersect(&region(-50, 0, 40, 40), (0, 0), (100, 100)), None);
+        assert_eq!(intersect(&region(100, 0, 40, 40), (0, 0), (100, 100)), None);
+    }
+}
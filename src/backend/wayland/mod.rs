@@ -7,6 +7,7 @@ use std::io::Read;
 use wayland_client::protocol::wl_shm::Format;
 
 pub mod wayland_error;
+pub mod wayland_frame_grabber;
 pub(crate) mod wayland_frame_meta;
 pub(crate) mod wayland_geometry;
 pub(crate) mod wayland_output_info;
@@ -90,6 +91,17 @@ pub fn create_screenshots() -> Result<Vec<(OutputInfo, DynamicImage)>, WaylandEr
     Ok(screenshots)
 }
 
+/// Returns the names of all outputs.
+pub fn get_output_names() -> Result<Vec<String>, WaylandError> {
+    let mut manager = WaylandScreenshotManager::new()?;
+
+    Ok(manager
+        .get_outputs()?
+        .iter()
+        .map(|output| output.name.clone())
+        .collect())
+}
+
 // Transforms the buffer containing our image from the wayland compositor into a `image::DynamicImage`.
 fn image_from_wayland(
     data: Vec<u8>,
@@ -31,6 +31,9 @@ pub enum WaylandError {
     #[error("The Wayland Compositor did not provide a format for the screenshot it made")]
     MissingFormat,
 
+    #[error("Your wayland compositor returned an unsupported buffer format: {0:?}. You might want to open an issue on GitHub.")]
+    UnsupportedFormat(wayland_client::protocol::wl_shm::Format),
+
     #[error("An error happened.")]
     GenericError(&'static str),
 }
@@ -0,0 +1,147 @@
+use crate::backend::wayland::wayland_error::WaylandError;
+use crate::backend::wayland::wayland_screenshot_manager::WaylandScreenshotManager;
+use crate::backend::Region;
+use image::RgbaImage;
+use std::io::Read;
+use std::time::Duration;
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::protocol::wl_shm::Format;
+
+///
+/// Grabs repeated frames of a region of a single wayland output.
+///
+/// The first frame is copied immediately, every following frame is requested with
+/// `copy_with_damage` so the compositor only answers if the region actually changed.
+///
+pub struct WaylandFrameGrabber {
+    manager: WaylandScreenshotManager,
+    output: WlOutput,
+
+    /// The region relative to the top-left corner of `output`.
+    local_region: Region,
+
+    with_damage: bool,
+}
+
+impl WaylandFrameGrabber {
+    pub fn new(region: Region) -> Result<Self, WaylandError> {
+        let mut manager = WaylandScreenshotManager::new()?;
+
+        let (output, local_region) = {
+            let output = manager
+                .get_outputs()?
+                .iter()
+                .find(|output| {
+                    let scale = output.scale.max(1);
+                    let x = output.geometry.x;
+                    let y = output.geometry.y;
+                    let width = output.mode.width / scale;
+                    let height = output.mode.height / scale;
+
+                    x <= region.x && region.x < x + width && y <= region.y && region.y < y + height
+                })
+                .ok_or(WaylandError::GenericError(
+                    "The region to record doesn't lie on any output",
+                ))?;
+
+            let local_region = Region {
+                x: region.x - output.geometry.x,
+                y: region.y - output.geometry.y,
+                ..region
+            };
+
+            (output.output.clone(), local_region)
+        };
+
+        Ok(Self {
+            manager,
+            output,
+            local_region,
+            with_damage: false,
+        })
+    }
+
+    /// Returns the next frame or `None` if the region didn't change within `timeout`.
+    pub fn next_frame(&mut self, timeout: Duration) -> Result<Option<RgbaImage>, WaylandError> {
+        let queue_handle = self.manager.get_queue_handle();
+        let screencopy_manager = self.manager.get_zwlr_screencopy_manager_v1()?.clone();
+
+        let frame = screencopy_manager.capture_output_region(
+            0,
+            &self.output,
+            self.local_region.x,
+            self.local_region.y,
+            self.local_region.width as i32,
+            self.local_region.height as i32,
+            &queue_handle,
+            (),
+        );
+
+        let mut shared_memory = self.manager.create_shared_memory()?;
+        if self.with_damage {
+            frame.copy_with_damage(shared_memory.get_buffer());
+        } else {
+            frame.copy(shared_memory.get_buffer());
+        }
+
+        let is_ready = self.manager.await_screenshot_timeout(timeout)?;
+
+        let result = if is_ready {
+            let mut data = vec![];
+            shared_memory
+                .get_memfile()
+                .read_to_end(&mut data)
+                .map_err(|_| WaylandError::GenericError("Couldn't read shared memory file"))?;
+
+            self.with_damage = true;
+
+            Some(rgba_from_wayland(
+                &data,
+                shared_memory.width(),
+                shared_memory.height(),
+                shared_memory.stride(),
+                shared_memory.format(),
+            )?)
+        } else {
+            None
+        };
+
+        frame.destroy();
+        shared_memory.destroy();
+        self.manager.next_screen();
+
+        Ok(result)
+    }
+}
+
+// Converts the buffer of the compositor into an opaque rgba image while respecting the stride.
+fn rgba_from_wayland(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: Format,
+) -> Result<RgbaImage, WaylandError> {
+    let bytes_per_pixel = match format {
+        Format::Argb8888 | Format::Xrgb8888 | Format::Abgr8888 | Format::Xbgr8888 => 4,
+        Format::Bgr888 => 3,
+        _ => return Err(WaylandError::UnsupportedFormat(format)),
+    };
+
+    let row_len = (width * bytes_per_pixel) as usize;
+
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for row in data.chunks(stride as usize).take(height as usize) {
+        for pixel in row[..row_len].chunks_exact(bytes_per_pixel as usize) {
+            // the formats are little endian, so `Argb8888` is stored as `[b, g, r, a]`
+            let [r, g, b] = match format {
+                Format::Argb8888 | Format::Xrgb8888 => [pixel[2], pixel[1], pixel[0]],
+                _ => [pixel[0], pixel[1], pixel[2]],
+            };
+
+            rgba.extend_from_slice(&[r, g, b, u8::MAX]);
+        }
+    }
+
+    RgbaImage::from_vec(width, height, rgba).ok_or(WaylandError::ConvertImageFailed)
+}
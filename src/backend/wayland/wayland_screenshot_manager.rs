@@ -2,7 +2,8 @@ use crate::backend::wayland::wayland_error::WaylandError;
 use crate::backend::wayland::wayland_output_info::WaylandOutputInfo;
 use crate::backend::wayland::wayland_screenshot_state::WaylandScreenshotState;
 use crate::backend::wayland::wayland_shared_memory::WaylandSharedMemory;
-use std::time::SystemTime;
+use rustix::event::{PollFd, PollFlags};
+use std::time::{Duration, SystemTime};
 use wayland_client::{Connection, EventQueue, QueueHandle};
 use wayland_protocols_wlr::screencopy::v1::client::zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1;
 
@@ -61,6 +62,27 @@ impl WaylandScreenshotManager {
         self.poll_queue_until(|state| state.screenshot_ready)
     }
 
+    /// Same as [`Self::await_screenshot`] but gives up after the given `timeout`.
+    ///
+    /// # Return value
+    /// `true` if the screenshot is ready, `false` if the timeout elapsed.
+    pub fn await_screenshot_timeout(&mut self, timeout: Duration) -> Result<bool, WaylandError> {
+        let start = SystemTime::now();
+
+        while !self.state.screenshot_ready {
+            let elapsed = SystemTime::now()
+                .duration_since(start)
+                .map_err(|_| WaylandError::GenericError("Failed to read system time"))?;
+
+            match timeout.checked_sub(elapsed) {
+                Some(remaining) if !remaining.is_zero() => self.dispatch_with_timeout(remaining)?,
+                _ => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
     pub fn create_shared_memory(&mut self) -> Result<WaylandSharedMemory, WaylandError> {
         self.poll_queue_until(|state| state.current_frame.is_some())?;
 
@@ -114,6 +136,36 @@ impl WaylandScreenshotManager {
         Ok(())
     }
 
+    fn dispatch_with_timeout(&mut self, timeout: Duration) -> Result<(), WaylandError> {
+        self.connection.flush().map_err(WaylandError::from)?;
+
+        // `None` means that there are still events in the queue which have to be dispatched first
+        if let Some(guard) = self.queue.prepare_read() {
+            let has_events = {
+                let mut fds = [PollFd::from_borrowed_fd(
+                    guard.connection_fd(),
+                    PollFlags::IN | PollFlags::ERR,
+                )];
+
+                let timeout_ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+                rustix::event::poll(&mut fds, timeout_ms)
+                    .map_err(|_| WaylandError::GenericError("Couldn't poll the wayland socket"))?
+                    > 0
+            };
+
+            // dropping the guard without reading cancels the read
+            if has_events {
+                guard.read().map_err(WaylandError::from)?;
+            }
+        }
+
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .map_err(WaylandError::from)?;
+
+        Ok(())
+    }
+
     pub fn next_screen(&mut self) {
         self.state.screenshot_ready = false;
         self.state.current_frame = None;
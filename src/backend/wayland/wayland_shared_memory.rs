@@ -17,6 +17,7 @@ use super::wayland_error::WaylandError;
 pub struct WaylandSharedMemory {
     width: u32,
     height: u32,
+    stride: u32,
     format: Format,
     memfile: File,
     shm_pool: WlShmPool,
@@ -51,6 +52,7 @@ impl WaylandSharedMemory {
         Ok(Self {
             width,
             height,
+            stride,
             format,
             memfile,
             shm_pool,
@@ -77,6 +79,9 @@ impl WaylandSharedMemory {
     pub fn height(&self) -> u32 {
         self.height
     }
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
     pub fn format(&self) -> Format {
         self.format
     }
@@ -2,11 +2,14 @@
 use image::{DynamicImage, RgbImage, RgbaImage};
 use x11rb::{
     connection::Connection,
-    protocol::xproto::{ImageFormat, ImageOrder, Screen},
+    protocol::{
+        randr::MonitorInfo as MonitorInfoReply,
+        xproto::{AtomEnum, ImageFormat, ImageOrder, Screen},
+    },
     rust_connection::RustConnection,
 };
 
-use super::{MonitorInfo, OutputInfo};
+use super::{MonitorInfo, OutputInfo, Region};
 
 /// A general enum with possible errors as values which can occur while
 /// operating with the xorg-server.
@@ -23,6 +26,9 @@ pub enum Error {
 
     #[error(transparent)]
     StringUtf8(#[from] std::string::FromUtf8Error),
+
+    #[error("The window manager doesn't tell which window is active.")]
+    NoActiveWindow,
 }
 
 /// The main function of this module.
@@ -72,23 +78,8 @@ pub fn create_screenshots() -> Result<Vec<(OutputInfo, image::DynamicImage)>, Er
                 monitor.width,
                 monitor.height,
             )?;
-            let monitor_info = {
-                let screen_resources = conn
-                    .randr_get_screen_resources_current(screen.root)?
-                    .reply()?;
-
-                let output_name = {
-                    let output_info = conn
-                        .randr_get_output_info(
-                            monitor.outputs[0],
-                            screen_resources.config_timestamp,
-                        )?
-                        .reply()?;
-
-                    String::from_utf8(output_info.name)?
-                };
-
-                MonitorInfo::X11 { name: output_name }
+            let monitor_info = MonitorInfo::X11 {
+                name: get_output_name(&conn, screen, monitor)?,
             };
 
             let output_info = OutputInfo {
@@ -109,6 +100,111 @@ pub fn create_screenshots() -> Result<Vec<(OutputInfo, image::DynamicImage)>, Er
     Ok(images)
 }
 
+/// Returns the names of all outputs.
+pub fn get_output_names() -> Result<Vec<String>, Error> {
+    use x11rb::protocol::randr::ConnectionExt;
+
+    let (conn, _) = x11rb::connect(None)?;
+
+    let mut names = vec![];
+    for screen in &conn.setup().roots {
+        let monitors = conn.randr_get_monitors(screen.root, true)?.reply()?;
+
+        for monitor in monitors.monitors.iter().filter(|m| !m.outputs.is_empty()) {
+            names.push(get_output_name(&conn, screen, monitor)?);
+        }
+    }
+
+    Ok(names)
+}
+
+fn get_output_name(
+    conn: &RustConnection,
+    screen: &Screen,
+    monitor: &MonitorInfoReply,
+) -> Result<String, Error> {
+    use x11rb::protocol::randr::ConnectionExt;
+
+    let screen_resources = conn
+        .randr_get_screen_resources_current(screen.root)?
+        .reply()?;
+
+    let output_info = conn
+        .randr_get_output_info(monitor.outputs[0], screen_resources.config_timestamp)?
+        .reply()?;
+
+    Ok(String::from_utf8(output_info.name)?)
+}
+
+/// Returns the geometry of the active window (according to `_NET_ACTIVE_WINDOW`) in root
+/// coordinates.
+pub fn get_active_window_region() -> Result<Region, Error> {
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let active_window_atom = conn.intern_atom(true, b"_NET_ACTIVE_WINDOW")?.reply()?.atom;
+    let window = conn
+        .get_property(false, root, active_window_atom, AtomEnum::WINDOW, 0, 1)?
+        .reply()?
+        .value32()
+        .and_then(|mut values| values.next())
+        .filter(|&window| window != x11rb::NONE)
+        .ok_or(Error::NoActiveWindow)?;
+
+    let geometry = conn.get_geometry(window)?.reply()?;
+    let position = conn.translate_coordinates(window, root, 0, 0)?.reply()?;
+
+    Ok(Region {
+        x: i32::from(position.dst_x),
+        y: i32::from(position.dst_y),
+        width: u32::from(geometry.width),
+        height: u32::from(geometry.height),
+    })
+}
+
+/// Grabs repeated frames of a region of the default screen.
+pub struct X11FrameGrabber {
+    conn: RustConnection,
+    screen_num: usize,
+    region: Region,
+}
+
+impl X11FrameGrabber {
+    pub fn new(region: Region) -> Result<Self, Error> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+
+        Ok(Self {
+            conn,
+            screen_num,
+            region,
+        })
+    }
+
+    pub fn next_frame(&mut self) -> Result<RgbaImage, Error> {
+        let screen = &self.conn.setup().roots[self.screen_num];
+
+        let image = get_image(
+            &self.conn,
+            screen,
+            self.region.x as i16,
+            self.region.y as i16,
+            self.region.width as u16,
+            self.region.height as u16,
+        )?;
+
+        let mut frame = image.to_rgba8();
+
+        // the xorg-server doesn't give any guarantees about the alpha channel
+        for pixel in frame.pixels_mut() {
+            pixel.0[3] = u8::MAX;
+        }
+
+        Ok(frame)
+    }
+}
+
 fn get_image(
     conn: &RustConnection,
     screen: &Screen,
@@ -1,9 +1,14 @@
 //! Contains the Cli implementation of flakeshot.
 use std::{fmt::Display, path::PathBuf};
 
-use clap::{crate_name, Parser, Subcommand, ValueEnum};
+use clap::{crate_name, Args, Parser, Subcommand, ValueEnum};
 use tracing::level_filters::LevelFilter;
 
+use crate::{
+    backend::{CaptureTarget, Region},
+    recorder::encoder::AnimationFormat,
+};
+
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 pub struct Cli {
@@ -27,20 +32,177 @@ pub struct Cli {
 
 impl Cli {
     pub fn command(&self) -> Command {
-        self.command.unwrap_or(Command::Tray)
+        self.command.clone().unwrap_or(Command::Tray)
     }
 }
 
-#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
 pub enum Command {
     /// Open the manual capture ui
-    Gui,
+    Gui(GuiArgs),
+
+    /// Open an existing image in the editor.
+    Edit(EditArgs),
+
+    /// Take a screenshot without opening the capture ui.
+    Capture(CaptureArgs),
+
+    /// Open the capture ui to select a region which should be recorded.
+    Record(RecordArgs),
+
+    /// Take a screenshot every few seconds and store them into a directory.
+    Timelapse(TimelapseArgs),
+
+    /// Manage the screenshot history.
+    #[command(subcommand)]
+    History(HistoryCommand),
 
     /// Start the system tray of flakeshot. (default)
     Tray,
 
     /// Print the default config to stdout.
     PrintDefaultConfig,
+
+    /// Serve the clipboard content on stdin until another client replaces it. Used internally
+    /// so the clipboard outlives the process which copied.
+    #[command(hide = true)]
+    ServeClipboard,
+}
+
+/// The arguments of [`Command::Gui`].
+#[derive(Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuiArgs {
+    /// Store the screenshot in the directory of the config instead of asking for a path.
+    #[arg(long)]
+    pub quick_save: bool,
+
+    /// Write the screenshot to stdout once it's confirmed (Enter, save, copy or upload).
+    /// Exits with 0 if the screenshot has been confirmed and with 1 if it has been cancelled.
+    #[arg(long, conflicts_with = "quick_save")]
+    pub stdout: bool,
+}
+
+/// The arguments of [`Command::Edit`].
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct EditArgs {
+    /// The image or project (`.flakeshot`) which should be edited. Use `-` to read an image
+    /// from stdin.
+    #[arg(required_unless_present = "from_clipboard")]
+    pub path: Option<PathBuf>,
+
+    /// Edit the image which is currently in the clipboard.
+    #[arg(long, conflicts_with = "path")]
+    pub from_clipboard: bool,
+}
+
+/// The arguments of [`Command::Capture`].
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct CaptureArgs {
+    /// Only capture the output with the given name (for example `DP-1`).
+    #[arg(long, conflicts_with_all = ["region", "active_window"])]
+    pub output: Option<String>,
+
+    /// Only capture the given region. Format: `<x>,<y> <width>x<height>`
+    #[arg(long, conflicts_with = "active_window")]
+    pub region: Option<Region>,
+
+    /// Only capture the window which currently has the focus. (X11 only)
+    #[arg(long)]
+    pub active_window: bool,
+
+    /// The file where the screenshot should be stored. The format is deduced from its extension.
+    /// Use `-` to write it to stdout in the export format of the config.
+    pub path: PathBuf,
+}
+
+impl CaptureArgs {
+    pub fn target(&self) -> CaptureTarget {
+        match (&self.output, self.region) {
+            (Some(output), _) => CaptureTarget::Output(output.clone()),
+            (None, Some(region)) => CaptureTarget::Region(region),
+            (None, None) if self.active_window => CaptureTarget::ActiveWindow,
+            (None, None) => CaptureTarget::Screen,
+        }
+    }
+}
+
+/// The arguments of [`Command::Record`]. Each of them overwrites the value of the config.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct RecordArgs {
+    /// Stop the recording of the running daemon instead of starting a new one.
+    #[arg(long)]
+    pub stop: bool,
+
+    /// The format of the recording.
+    #[arg(short, long)]
+    pub format: Option<AnimationFormat>,
+
+    /// How many frames should be captured per second.
+    #[arg(long)]
+    pub fps: Option<u32>,
+
+    /// The maximum length of the recording in seconds.
+    #[arg(short, long)]
+    pub duration: Option<u64>,
+
+    /// The file where the recording should be stored.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// The arguments of [`Command::Timelapse`]. Each of them overwrites the value of the config.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct TimelapseArgs {
+    /// The number of seconds between two screenshots.
+    #[arg(short, long)]
+    pub interval: Option<u64>,
+
+    /// The directory where the screenshots should be stored.
+    #[arg(short, long)]
+    pub directory: Option<PathBuf>,
+
+    /// Stop after the given amount of screenshots.
+    #[arg(long)]
+    pub max_count: Option<u32>,
+
+    /// Stop after the given amount of seconds.
+    #[arg(long)]
+    pub max_duration: Option<u64>,
+
+    /// Don't store a screenshot if nothing changed since the previous one.
+    #[arg(long, overrides_with = "keep_unchanged")]
+    pub skip_unchanged: bool,
+
+    /// Store a screenshot even if nothing changed since the previous one.
+    #[arg(long, overrides_with = "skip_unchanged")]
+    pub keep_unchanged: bool,
+
+    /// Only capture the output with the given name (for example `DP-1`).
+    #[arg(long, conflicts_with = "region")]
+    pub output: Option<String>,
+
+    /// Only capture the given region. Format: `<x>,<y> <width>x<height>`
+    #[arg(long)]
+    pub region: Option<Region>,
+}
+
+impl TimelapseArgs {
+    /// Returns `None` if neither `--skip-unchanged` nor `--keep-unchanged` is given, so the
+    /// value of the config is used.
+    pub fn skip_unchanged(&self) -> Option<bool> {
+        match (self.skip_unchanged, self.keep_unchanged) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// The subcommands of [`Command::History`].
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum HistoryCommand {
+    /// Remove all screenshots which violate the retention policy of the config.
+    Prune,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -0,0 +1,281 @@
+// The wlr and ext data control protocols are identical apart from their names, so this file is
+// included by both `wayland::wlr` and `wayland::ext` which import the protocol under the same
+// aliases: `manager`, `device`, `source`, `offer` and `Manager`, `Device`, `Source`, `Offer`.
+
+/// Holds the state of our selection while we're serving it.
+struct State {
+    offers: Vec<super::super::Offer>,
+    cancelled: bool,
+}
+
+/// Takes over the clipboard and serves the offers in a new thread until another client takes
+/// over the clipboard.
+pub fn serve(offers: Vec<super::super::Offer>) -> Result<JoinHandle<()>, Error> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+    let qh = queue.handle();
+
+    let manager: Manager = globals
+        .bind(&qh, 1..=Manager::interface().version, ())
+        .map_err(|_| Error::NoDataControl)?;
+    let seat: WlSeat = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|_| Error::NoSeat)?;
+
+    let source = manager.create_data_source(&qh, ());
+    for offer in &offers {
+        source.offer(offer.mime_type.clone());
+    }
+
+    let device = manager.get_data_device(&seat, &qh, ());
+    device.set_selection(Some(&source));
+
+    let mut state = State {
+        offers,
+        cancelled: false,
+    };
+    queue.roundtrip(&mut state)?;
+
+    Ok(std::thread::spawn(move || {
+        while !state.cancelled {
+            if let Err(err) = queue.blocking_dispatch(&mut state) {
+                error!("Stopped serving the clipboard: {}", err);
+                break;
+            }
+        }
+
+        device.destroy();
+        source.destroy();
+    }))
+}
+
+impl Dispatch<Source, ()> for State {
+    fn event(
+        state: &mut Self,
+        _source: &Source,
+        event: source::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            source::Event::Send { mime_type, fd } => {
+                let Some(offer) = state
+                    .offers
+                    .iter()
+                    .find(|offer| offer.mime_type == mime_type)
+                else {
+                    return;
+                };
+
+                if let Err(err) = File::from(fd).write_all(&offer.data) {
+                    warn!("Couldn't send the clipboard content: {}", err);
+                }
+            }
+            source::Event::Cancelled => state.cancelled = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<Device, ()> for State {
+    fn event(
+        state: &mut Self,
+        _device: &Device,
+        event: device::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let device::Event::Finished = event {
+            state.cancelled = true;
+        }
+    }
+
+    event_created_child!(State, Device, [
+        device::EVT_DATA_OFFER_OPCODE => (Offer, ()),
+    ]);
+}
+
+impl Dispatch<Offer, ()> for State {
+    fn event(
+        _state: &mut Self,
+        offer: &Offer,
+        _event: offer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // we don't read the clipboard while serving it, so we don't care about the offers of
+        // other clients
+        offer.destroy();
+    }
+}
+
+delegate_noop!(State: ignore Manager);
+delegate_noop!(State: ignore WlSeat);
+
+/// Collects the offers of other clients while we're reading the clipboard.
+#[derive(Default)]
+struct ReadState {
+    /// The mime types of each offer.
+    mime_types: HashMap<ObjectId, Vec<String>>,
+    selection: Option<Offer>,
+}
+
+/// Reads the first image representation of the clipboard.
+///
+/// # Return value
+/// `None` if the clipboard is empty or doesn't contain an image.
+pub fn read_image() -> Result<Option<super::super::Offer>, Error> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<ReadState>(&conn)?;
+    let qh = queue.handle();
+
+    let manager: Manager = globals
+        .bind(&qh, 1..=Manager::interface().version, ())
+        .map_err(|_| Error::NoDataControl)?;
+    let seat: WlSeat = globals
+        .bind(&qh, 1..=1, ())
+        .map_err(|_| Error::NoSeat)?;
+
+    // the compositor sends the current selection right after the device has been created
+    let device = manager.get_data_device(&seat, &qh, ());
+    let mut state = ReadState::default();
+    queue.roundtrip(&mut state)?;
+
+    let Some(offer) = state.selection.take() else {
+        device.destroy();
+        return Ok(None);
+    };
+
+    let mime_types = state.mime_types.remove(&offer.id()).unwrap_or_default();
+    let result = match super::super::pick_image_mime_type(&mime_types) {
+        Some(mime_type) => receive(&mut queue, &mut state, &offer, mime_type).map(|data| {
+            Some(super::super::Offer {
+                mime_type: mime_type.to_string(),
+                data,
+            })
+        }),
+        None => Ok(None),
+    };
+
+    offer.destroy();
+    device.destroy();
+    queue.roundtrip(&mut state)?;
+
+    result
+}
+
+/// Asks the owner of the clipboard to write the content into a pipe and reads it. Fails with
+/// [`Error::Timeout`] if the owner doesn't finish in time.
+fn receive(
+    queue: &mut EventQueue<ReadState>,
+    state: &mut ReadState,
+    offer: &Offer,
+    mime_type: &str,
+) -> Result<Vec<u8>, Error> {
+    let (reader, writer) = rustix::pipe::pipe_with(rustix::pipe::PipeFlags::CLOEXEC)
+        .map_err(std::io::Error::from)?;
+
+    offer.receive(mime_type.to_string(), writer.as_fd());
+    queue.roundtrip(state)?;
+
+    // the owner holds its own copy of the fd, we'd never see the end of the pipe otherwise
+    drop(writer);
+
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let mut reader = File::from(reader);
+    let mut data = vec![];
+    let mut buffer = [0; 64 * 1024];
+
+    loop {
+        let is_readable = {
+            let mut fds = [PollFd::new(&reader, PollFlags::IN)];
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let timeout_ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+            match rustix::event::poll(&mut fds, timeout_ms) {
+                Ok(ready) => ready > 0,
+                Err(rustix::io::Errno::INTR) => continue,
+                Err(err) => return Err(std::io::Error::from(err).into()),
+            }
+        };
+
+        if !is_readable {
+            return Err(Error::Timeout);
+        }
+
+        match reader.read(&mut buffer)? {
+            0 => return Ok(data),
+            len => data.extend_from_slice(&buffer[..len]),
+        }
+    }
+}
+
+impl Dispatch<Device, ()> for ReadState {
+    fn event(
+        state: &mut Self,
+        _device: &Device,
+        event: device::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let device::Event::Selection { id } = event {
+            if let Some(previous) = std::mem::replace(&mut state.selection, id) {
+                previous.destroy();
+            }
+        }
+    }
+
+    event_created_child!(ReadState, Device, [
+        device::EVT_DATA_OFFER_OPCODE => (Offer, ()),
+    ]);
+}
+
+impl Dispatch<Offer, ()> for ReadState {
+    fn event(
+        state: &mut Self,
+        offer: &Offer,
+        event: offer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let offer::Event::Offer { mime_type } = event {
+            state
+                .mime_types
+                .entry(offer.id())
+                .or_default()
+                .push(mime_type);
+        }
+    }
+}
+
+delegate_noop!(ReadState: ignore Manager);
+delegate_noop!(ReadState: ignore WlSeat);
+
+impl Dispatch<WlRegistry, GlobalListContents> for ReadState {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
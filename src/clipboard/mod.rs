@@ -0,0 +1,524 @@
+//! Copies images into the clipboard and reads them from it.
+//!
+//! flakeshot owns the clipboard itself as long as possible. The external clipboard manager of
+//! the config is only used as a fallback, for example if the compositor doesn't support any
+//! data control protocol.
+//!
+//! The content of the clipboard vanishes with its owner, so it's served by the daemon or by a
+//! detached child process (like `wl-copy` and `xclip` do) if flakeshot isn't running as daemon.
+use std::{
+    io::{BufRead, BufReader, BufWriter, Write},
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::Path,
+    process::Stdio,
+    sync::Mutex,
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::Context;
+use base64::Engine;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{self, Config},
+    export::{self, ExportFormat},
+    get_xdg,
+    tray::{
+        self,
+        control::{self, Request},
+        dbus,
+    },
+};
+
+pub mod wayland;
+pub mod x11;
+
+/// The file which `text/uri-list` points to.
+const TEMP_FILENAME: &str = "clipboard.png";
+
+const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+
+/// The log file of the detached clipboard server, so it doesn't truncate the log of its parent.
+const SERVER_LOG_FILENAME: &str = "clipboard.log";
+
+/// Written by the detached clipboard server once it owns the clipboard.
+const SERVER_READY: &str = "ready";
+
+/// How long we wait for the owner of the clipboard to answer a request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The thread which currently serves our clipboard content.
+static SERVER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    X11Connect(#[from] x11rb::errors::ConnectError),
+
+    #[error(transparent)]
+    X11Connection(#[from] x11rb::errors::ConnectionError),
+
+    #[error(transparent)]
+    X11Reply(#[from] x11rb::errors::ReplyError),
+
+    #[error(transparent)]
+    X11ReplyOrId(#[from] x11rb::errors::ReplyOrIdError),
+
+    #[error(transparent)]
+    WaylandConnect(#[from] wayland_client::ConnectError),
+
+    #[error(transparent)]
+    WaylandDispatch(#[from] wayland_client::DispatchError),
+
+    #[error(transparent)]
+    WaylandGlobal(#[from] wayland_client::globals::GlobalError),
+
+    #[error("The compositor doesn't support the ext-data-control or wlr-data-control protocol.")]
+    NoDataControl,
+
+    #[error("The compositor doesn't provide a seat.")]
+    NoSeat,
+
+    #[error("Another client took over the clipboard immediately.")]
+    OwnershipLost,
+
+    #[error("The owner of the clipboard didn't answer.")]
+    Timeout,
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// The content of the clipboard in one representation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Offer {
+    pub mime_type: String,
+    /// Stored as base64 when it's serialized, an array of numbers would be way bigger.
+    #[serde(with = "base64_data")]
+    pub data: Vec<u8>,
+}
+
+mod base64_data {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Copies the image into the clipboard. It's offered in the export format of the config and in
+/// the additional representations of `clipboard_offers`.
+pub fn copy_image(config: &Config, img: &DynamicImage) -> anyhow::Result<()> {
+    copy(config, image_offers(config, img)?)?;
+
+    dbus::emit_screenshot_copied();
+    Ok(())
+}
+
+/// Copies the text, for example the URL of an upload, into the clipboard.
+pub fn copy_text(config: &Config, text: &str) -> anyhow::Result<()> {
+    let offers = ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"]
+        .into_iter()
+        .map(|mime_type| Offer {
+            mime_type: mime_type.to_string(),
+            data: text.as_bytes().to_vec(),
+        })
+        .collect();
+
+    copy(config, offers)
+}
+
+/// Returns all representations of the image, starting with the export format.
+fn image_offers(config: &Config, img: &DynamicImage) -> anyhow::Result<Vec<Offer>> {
+    let format = config.export.format;
+    let mut offers = vec![Offer {
+        mime_type: format.mime_type().to_string(),
+        data: export::encode_to_vec(img, format, &config.export)?,
+    }];
+
+    // PNG is understood by almost every application, so it's always offered
+    let png = if format == ExportFormat::Png {
+        offers[0].data.clone()
+    } else {
+        let png = export::encode_to_vec(img, ExportFormat::Png, &config.export)?;
+        offers.push(Offer {
+            mime_type: ExportFormat::Png.mime_type().to_string(),
+            data: png.clone(),
+        });
+        png
+    };
+
+    let options = &config.clipboard_offers;
+    if options.jpeg && format != ExportFormat::Jpeg {
+        offers.push(Offer {
+            mime_type: ExportFormat::Jpeg.mime_type().to_string(),
+            data: export::encode_to_vec(img, ExportFormat::Jpeg, &config.export)?,
+        });
+    }
+
+    // the file itself is written by `serve`, the clipboard managers only get the first offer
+    if options.uri_list {
+        let path = get_xdg()
+            .place_runtime_file(TEMP_FILENAME)
+            .context("Couldn't create the temporary file of the clipboard")?;
+
+        offers.push(Offer {
+            mime_type: URI_LIST_MIME_TYPE.to_string(),
+            data: format!("{}\r\n", file_uri(&path)).into_bytes(),
+        });
+    }
+
+    if options.html {
+        let html = format!(
+            "<img src=\"data:image/png;base64,{}\">",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        );
+
+        offers.push(Offer {
+            mime_type: "text/html".to_string(),
+            data: html.into_bytes(),
+        });
+    }
+
+    Ok(offers)
+}
+
+/// Converts the absolute path into a `file://` URI (RFC 8089).
+pub(crate) fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+/// Puts the offers into the clipboard.
+///
+/// The first offer is the one which is given to the external clipboard manager if we can't own
+/// the clipboard ourself.
+pub fn copy(config: &Config, offers: Vec<Offer>) -> anyhow::Result<()> {
+    let clipboard = if crate::backend::is_wayland() {
+        &config.wayland.clipboard
+    } else {
+        &config.x11.clipboard
+    };
+
+    if clipboard.native {
+        match own(offers.clone()) {
+            Ok(()) => return Ok(()),
+            Err(err) => warn!(
+                "Couldn't own the clipboard, using '{}' instead: {:#}",
+                clipboard.cmd(),
+                err
+            ),
+        }
+    }
+
+    let offer = offers.first().context("There's nothing to copy.")?;
+    copy_with_command(clipboard, offer)
+}
+
+/// Serves the offers until another client takes over the clipboard.
+///
+/// Only the daemon serves them itself. Other processes hand them over to the daemon or to a
+/// detached child process since both live longer than we do.
+fn own(offers: Vec<Offer>) -> anyhow::Result<()> {
+    if tray::is_running() {
+        serve(offers)?;
+    } else if control::is_daemon_running() {
+        debug!("Handing the clipboard over to the daemon.");
+        control::send(&Request::SetClipboard(offers))?;
+    } else {
+        debug!("Handing the clipboard over to a detached server.");
+        spawn_server(&offers)?;
+    }
+
+    Ok(())
+}
+
+/// Starts `flakeshot serve-clipboard` in its own process group and waits until it owns the
+/// clipboard.
+fn spawn_server(offers: &[Offer]) -> anyhow::Result<()> {
+    let exe = std::env::current_exe().context("Couldn't find the flakeshot executable")?;
+    let log_path = get_xdg()
+        .place_state_file(SERVER_LOG_FILENAME)
+        .context("Couldn't access the log file of the clipboard server")?;
+
+    let mut child = std::process::Command::new(exe)
+        .arg("--log-path")
+        .arg(log_path)
+        .arg("serve-clipboard")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        // a closed terminal mustn't take the clipboard down with it
+        .process_group(0)
+        .spawn()
+        .context("Couldn't spawn the clipboard server")?;
+
+    let mut stdin = BufWriter::new(
+        child
+            .stdin
+            .take()
+            .context("Couldn't get stdin of the clipboard server")?,
+    );
+    serde_json::to_writer(&mut stdin, offers)?;
+    stdin
+        .flush()
+        .context("Couldn't send the content to the clipboard server")?;
+    drop(stdin);
+
+    let mut answer = String::new();
+    BufReader::new(
+        child
+            .stdout
+            .take()
+            .context("Couldn't get stdout of the clipboard server")?,
+    )
+    .read_line(&mut answer)
+    .context("Couldn't read the answer of the clipboard server")?;
+
+    if answer.trim_end() != SERVER_READY {
+        let status = child.wait()?;
+        anyhow::bail!("The clipboard server failed ({})", status);
+    }
+
+    // reap the server once it lost the clipboard, as long as we are still alive
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Serves the offers which are read from stdin until another client takes over the clipboard.
+///
+/// It's the counterpart of [`spawn_server`] and runs in the detached child process.
+pub fn serve_from_stdin() -> anyhow::Result<()> {
+    let offers: Vec<Offer> = serde_json::from_reader(std::io::stdin().lock())
+        .context("Couldn't read the content of the clipboard from stdin")?;
+    serve(offers)?;
+
+    // the parent exits once it receives the answer
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", SERVER_READY)?;
+    stdout.flush()?;
+    drop(stdout);
+
+    wait_until_replaced();
+    Ok(())
+}
+
+/// Takes over the clipboard and serves the offers in a background thread.
+pub fn serve(offers: Vec<Offer>) -> Result<(), Error> {
+    write_temp_file(&offers)?;
+
+    let server = if crate::backend::is_wayland() {
+        wayland::serve(offers)?
+    } else {
+        x11::serve(offers)?
+    };
+
+    // the previous server stops on its own since it just lost the clipboard
+    *SERVER.lock().unwrap() = Some(server);
+    Ok(())
+}
+
+/// Writes the PNG which the `text/uri-list` offer of [`image_offers`] points to. Nothing is
+/// written if there's no such offer.
+fn write_temp_file(offers: &[Offer]) -> Result<(), Error> {
+    let find = |mime_type: &str| offers.iter().find(|offer| offer.mime_type == mime_type);
+
+    if let (Some(_), Some(png)) = (
+        find(URI_LIST_MIME_TYPE),
+        find(ExportFormat::Png.mime_type()),
+    ) {
+        let path = get_xdg().place_runtime_file(TEMP_FILENAME)?;
+        std::fs::write(path, &png.data)?;
+    }
+
+    Ok(())
+}
+
+/// Blocks until another client took over the clipboard. Returns immediately if we don't own the
+/// clipboard.
+fn wait_until_replaced() {
+    let server = SERVER.lock().unwrap().take();
+
+    if let Some(server) = server {
+        debug!("Serving the clipboard until another client replaces it.");
+        let _ = server.join();
+    }
+}
+
+/// Reads the image which is currently in the clipboard.
+pub fn paste_image(config: &Config) -> anyhow::Result<DynamicImage> {
+    let (clipboard, paste) = if crate::backend::is_wayland() {
+        (&config.wayland.clipboard, &config.wayland.paste)
+    } else {
+        (&config.x11.clipboard, &config.x11.paste)
+    };
+
+    let data = if clipboard.native {
+        match read_image() {
+            Ok(Some(offer)) => offer.data,
+            Ok(None) => anyhow::bail!("There's no image in the clipboard."),
+            Err(err) => {
+                warn!(
+                    "Couldn't read the clipboard, using '{}' instead: {:#}",
+                    paste.join(" "),
+                    err
+                );
+                paste_with_command(paste)?
+            }
+        }
+    } else {
+        paste_with_command(paste)?
+    };
+
+    image::load_from_memory(&data).context("Couldn't decode the image in the clipboard")
+}
+
+fn read_image() -> Result<Option<Offer>, Error> {
+    if crate::backend::is_wayland() {
+        wayland::read_image()
+    } else {
+        x11::read_image()
+    }
+}
+
+/// Returns the mime type of the best image representation: PNG is lossless and understood by
+/// everyone, so it's preferred over all other image formats.
+fn pick_image_mime_type(mime_types: &[String]) -> Option<&str> {
+    mime_types
+        .iter()
+        .find(|mime_type| *mime_type == "image/png")
+        .or_else(|| {
+            mime_types
+                .iter()
+                .find(|mime_type| mime_type.starts_with("image/"))
+        })
+        .map(String::as_str)
+}
+
+fn paste_with_command(paste: &[String]) -> anyhow::Result<Vec<u8>> {
+    let (cmd, args) = paste
+        .split_first()
+        .context("There's no command to read the clipboard.")?;
+
+    let output = std::process::Command::new(cmd)
+        .args(args.iter().map(|arg| arg.replace("{mime}", "image/png")))
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| {
+            format!(
+                "Coulnd't spawn '{}'. Is it a typo or did you really install it?",
+                cmd
+            )
+        })?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        anyhow::bail!("There's no image in the clipboard.");
+    }
+
+    Ok(output.stdout)
+}
+
+fn copy_with_command(clipboard: &config::Clipboard, offer: &Offer) -> anyhow::Result<()> {
+    let clip_man = clipboard.cmd();
+
+    let mut child = std::process::Command::new(clip_man)
+        .args(
+            clipboard
+                .args()
+                .iter()
+                .map(|arg| arg.replace("{mime}", &offer.mime_type)),
+        )
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            format!(
+                "Coulnd't spawn '{}'. Is it a typo or did you really install it?",
+                clip_man
+            )
+        })?;
+
+    let child_stdin = child
+        .stdin
+        .as_mut()
+        .context("Couldn't get stdin of clipboard-process")?;
+    child_stdin
+        .write_all(&offer.data)
+        .context("Couldn't write image bytes into clipboard")?;
+    child_stdin
+        .flush()
+        .context("Couldn't flush image to clipboard.")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_is_preferred_over_other_images() {
+        let mime_types = |types: &[&str]| -> Vec<String> {
+            types
+                .iter()
+                .map(|mime_type| mime_type.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            pick_image_mime_type(&mime_types(&["TARGETS", "image/jpeg", "image/png"])),
+            Some("image/png")
+        );
+        assert_eq!(
+            pick_image_mime_type(&mime_types(&["text/plain", "image/bmp"])),
+            Some("image/bmp")
+        );
+        assert_eq!(
+            pick_image_mime_type(&mime_types(&["text/plain", "UTF8_STRING"])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_offer_data_is_base64() {
+        let offer = Offer {
+            mime_type: "image/png".to_string(),
+            data: vec![0, 1, 2, 255],
+        };
+
+        let json = serde_json::to_string(&offer).unwrap();
+        assert_eq!(json, r#"{"mime_type":"image/png","data":"AAEC/w=="}"#);
+        assert_eq!(serde_json::from_str::<Offer>(&json).unwrap(), offer);
+    }
+
+    #[test]
+    fn test_file_uri_escapes_reserved_characters() {
+        assert_eq!(
+            file_uri(Path::new("/run/user/1000/flakeshot/clipboard.png")),
+            "file:///run/user/1000/flakeshot/clipboard.png"
+        );
+        assert_eq!(
+            file_uri(Path::new("/home/me/My Screenshots/100%.png")),
+            "file:///home/me/My%20Screenshots/100%25.png"
+        );
+    }
+}
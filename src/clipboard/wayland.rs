@@ -0,0 +1,67 @@
+//! Serves and reads the clipboard through the `ext-data-control` or `wlr-data-control`
+//! protocol.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    os::fd::AsFd,
+    thread::JoinHandle,
+    time::Instant,
+};
+
+use rustix::event::{PollFd, PollFlags};
+use tracing::{error, warn};
+use wayland_client::{
+    backend::ObjectId,
+    delegate_noop, event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{
+        wl_registry::{self, WlRegistry},
+        wl_seat::WlSeat,
+    },
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+
+use super::{Error, Offer, READ_TIMEOUT};
+
+/// Serves the offers with the first data control protocol which is supported by the compositor.
+pub fn serve(offers: Vec<Offer>) -> Result<JoinHandle<()>, Error> {
+    match ext::serve(offers.clone()) {
+        Err(Error::NoDataControl) => wlr::serve(offers),
+        result => result,
+    }
+}
+
+/// Reads the clipboard with the first data control protocol which is supported by the compositor.
+pub fn read_image() -> Result<Option<Offer>, Error> {
+    match ext::read_image() {
+        Err(Error::NoDataControl) => wlr::read_image(),
+        result => result,
+    }
+}
+
+mod ext {
+    use wayland_protocols::ext::data_control::v1::client::{
+        ext_data_control_device_v1::{self as device, ExtDataControlDeviceV1 as Device},
+        ext_data_control_manager_v1::ExtDataControlManagerV1 as Manager,
+        ext_data_control_offer_v1::{self as offer, ExtDataControlOfferV1 as Offer},
+        ext_data_control_source_v1::{self as source, ExtDataControlSourceV1 as Source},
+    };
+
+    use super::*;
+
+    include!("data_control.rs");
+}
+
+mod wlr {
+    use wayland_protocols_wlr::data_control::v1::client::{
+        zwlr_data_control_device_v1::{self as device, ZwlrDataControlDeviceV1 as Device},
+        zwlr_data_control_manager_v1::ZwlrDataControlManagerV1 as Manager,
+        zwlr_data_control_offer_v1::{self as offer, ZwlrDataControlOfferV1 as Offer},
+        zwlr_data_control_source_v1::{self as source, ZwlrDataControlSourceV1 as Source},
+    };
+
+    use super::*;
+
+    include!("data_control.rs");
+}
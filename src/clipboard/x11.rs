@@ -0,0 +1,382 @@
+//! Owns and reads the `CLIPBOARD` selection of the xorg-server.
+//!
+//! See the ICCCM for the details of the protocol, especially "2.5 HANDLING OF LARGE SELECTION
+//! TRANSFERS" for INCR transfers.
+use std::{
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use tracing::error;
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{
+            Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, CreateWindowAux, EventMask,
+            PropMode, Property, SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass,
+            SELECTION_NOTIFY_EVENT,
+        },
+        Event,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    CURRENT_TIME, NONE,
+};
+
+use super::{Error, Offer, READ_TIMEOUT};
+
+/// Content which is bigger than this is sent in chunks of this size (INCR).
+const INCR_CHUNK_SIZE: usize = 128 * 1024;
+
+/// The property of our window where the owner stores the requested content.
+const READ_PROPERTY: &str = "FLAKESHOT_CLIPBOARD";
+
+struct Atoms {
+    clipboard: Atom,
+    targets: Atom,
+    incr: Atom,
+}
+
+/// A running INCR transfer.
+struct Transfer {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+/// Takes over the clipboard and serves the offers in a new thread until another client takes
+/// over the clipboard.
+pub fn serve(offers: Vec<Offer>) -> Result<JoinHandle<()>, Error> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let screen = &conn.setup().roots[screen_num];
+
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?;
+
+    let atoms = Atoms {
+        clipboard: intern_atom(&conn, "CLIPBOARD")?,
+        targets: intern_atom(&conn, "TARGETS")?,
+        incr: intern_atom(&conn, "INCR")?,
+    };
+
+    let offers = offers
+        .into_iter()
+        .map(|offer| Ok((intern_atom(&conn, &offer.mime_type)?, offer.data)))
+        .collect::<Result<Vec<(Atom, Vec<u8>)>, Error>>()?;
+
+    conn.set_selection_owner(window, atoms.clipboard, CURRENT_TIME)?;
+    if conn.get_selection_owner(atoms.clipboard)?.reply()?.owner != window {
+        return Err(Error::OwnershipLost);
+    }
+
+    Ok(std::thread::spawn(move || {
+        if let Err(err) = handle_events(&conn, window, &atoms, &offers) {
+            error!("Stopped serving the clipboard: {}", err);
+        }
+
+        let _ = conn.destroy_window(window);
+        let _ = conn.flush();
+    }))
+}
+
+fn handle_events(
+    conn: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    offers: &[(Atom, Vec<u8>)],
+) -> Result<(), Error> {
+    let mut transfers: Vec<Transfer> = vec![];
+
+    loop {
+        match conn.wait_for_event()? {
+            Event::SelectionRequest(request) => {
+                let property = handle_request(conn, atoms, offers, &request, &mut transfers)?;
+
+                let notification = SelectionNotifyEvent {
+                    response_type: SELECTION_NOTIFY_EVENT,
+                    sequence: 0,
+                    time: request.time,
+                    requestor: request.requestor,
+                    selection: request.selection,
+                    target: request.target,
+                    property,
+                };
+                conn.send_event(false, request.requestor, EventMask::NO_EVENT, notification)?;
+                conn.flush()?;
+            }
+            Event::PropertyNotify(event) if event.state == Property::DELETE => {
+                let Some(index) = transfers.iter().position(|transfer| {
+                    transfer.requestor == event.window && transfer.property == event.atom
+                }) else {
+                    continue;
+                };
+
+                // the requestor read the previous chunk
+                let transfer = &mut transfers[index];
+                let end = (transfer.offset + INCR_CHUNK_SIZE).min(transfer.data.len());
+                conn.change_property8(
+                    PropMode::REPLACE,
+                    transfer.requestor,
+                    transfer.property,
+                    transfer.target,
+                    &transfer.data[transfer.offset..end],
+                )?;
+
+                // an empty chunk marks the end of the transfer
+                if transfer.offset == end {
+                    conn.change_window_attributes(
+                        transfer.requestor,
+                        &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+                    )?;
+                    transfers.remove(index);
+                } else {
+                    transfer.offset = end;
+                }
+                conn.flush()?;
+            }
+            Event::SelectionClear(event) if event.owner == window => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Stores the requested target in the property of the requestor.
+///
+/// # Return value
+/// The property which contains the answer or `NONE` if the request has been refused.
+fn handle_request(
+    conn: &RustConnection,
+    atoms: &Atoms,
+    offers: &[(Atom, Vec<u8>)],
+    request: &SelectionRequestEvent,
+    transfers: &mut Vec<Transfer>,
+) -> Result<Atom, Error> {
+    // obsolete clients don't set a property
+    let property = if request.property == NONE {
+        request.target
+    } else {
+        request.property
+    };
+
+    if request.selection != atoms.clipboard {
+        return Ok(NONE);
+    }
+
+    if request.target == atoms.targets {
+        let targets: Vec<Atom> = std::iter::once(atoms.targets)
+            .chain(offers.iter().map(|(atom, _)| *atom))
+            .collect();
+
+        conn.change_property32(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            AtomEnum::ATOM,
+            &targets,
+        )?;
+        return Ok(property);
+    }
+
+    let Some((target, data)) = offers.iter().find(|(atom, _)| *atom == request.target) else {
+        return Ok(NONE);
+    };
+
+    if data.len() <= INCR_CHUNK_SIZE {
+        conn.change_property8(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            *target,
+            data,
+        )?;
+        return Ok(property);
+    }
+
+    // the requestor deletes the property whenever it's ready for the next chunk
+    conn.change_window_attributes(
+        request.requestor,
+        &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )?;
+    conn.change_property32(
+        PropMode::REPLACE,
+        request.requestor,
+        property,
+        atoms.incr,
+        &[data.len() as u32],
+    )?;
+
+    transfers.push(Transfer {
+        requestor: request.requestor,
+        property,
+        target: *target,
+        data: data.clone(),
+        offset: 0,
+    });
+
+    Ok(property)
+}
+
+/// Reads the first image representation of the clipboard.
+///
+/// # Return value
+/// `None` if the clipboard is empty or doesn't contain an image.
+pub fn read_image() -> Result<Option<Offer>, Error> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let screen = &conn.setup().roots[screen_num];
+
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        // INCR transfers are driven by property changes
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )?;
+
+    let atoms = Atoms {
+        clipboard: intern_atom(&conn, "CLIPBOARD")?,
+        targets: intern_atom(&conn, "TARGETS")?,
+        incr: intern_atom(&conn, "INCR")?,
+    };
+    let property = intern_atom(&conn, READ_PROPERTY)?;
+
+    let result = read_image_with(&conn, window, &atoms, property);
+
+    conn.destroy_window(window)?;
+    conn.flush()?;
+
+    result
+}
+
+fn read_image_with(
+    conn: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    property: Atom,
+) -> Result<Option<Offer>, Error> {
+    if conn.get_selection_owner(atoms.clipboard)?.reply()?.owner == NONE {
+        return Ok(None);
+    }
+
+    let Some(targets) = convert(conn, window, atoms, property, atoms.targets)? else {
+        return Ok(None);
+    };
+
+    let mut mime_types = vec![];
+    for target in targets.chunks_exact(4) {
+        let atom = u32::from_ne_bytes([target[0], target[1], target[2], target[3]]);
+        let name = conn.get_atom_name(atom)?.reply()?.name;
+        mime_types.push(String::from_utf8_lossy(&name).into_owned());
+    }
+
+    let Some(mime_type) = super::pick_image_mime_type(&mime_types) else {
+        return Ok(None);
+    };
+
+    let target = intern_atom(conn, mime_type)?;
+    Ok(
+        convert(conn, window, atoms, property, target)?.map(|data| Offer {
+            mime_type: mime_type.to_string(),
+            data,
+        }),
+    )
+}
+
+/// Asks the owner of the clipboard to store the target in the property of our window.
+///
+/// # Return value
+/// `None` if the owner refused the request.
+fn convert(
+    conn: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    property: Atom,
+    target: Atom,
+) -> Result<Option<Vec<u8>>, Error> {
+    conn.convert_selection(window, atoms.clipboard, target, property, CURRENT_TIME)?;
+    conn.flush()?;
+
+    let Event::SelectionNotify(notification) = wait_for_event(
+        conn,
+        |event| matches!(event, Event::SelectionNotify(notification) if notification.requestor == window),
+    )?
+    else {
+        unreachable!("Only selection notifications are matched.");
+    };
+
+    if notification.property == NONE {
+        return Ok(None);
+    }
+
+    // deleting the property tells the owner that we're ready for the next INCR chunk
+    let reply = conn
+        .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX)?
+        .reply()?;
+    conn.flush()?;
+
+    if reply.type_ != atoms.incr {
+        return Ok(Some(reply.value));
+    }
+
+    let mut data = vec![];
+    loop {
+        wait_for_event(conn, |event| {
+            matches!(event, Event::PropertyNotify(event)
+                if event.window == window
+                    && event.atom == property
+                    && event.state == Property::NEW_VALUE)
+        })?;
+
+        let chunk = conn
+            .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?
+            .value;
+        conn.flush()?;
+
+        // an empty chunk marks the end of the transfer
+        if chunk.is_empty() {
+            return Ok(Some(data));
+        }
+        data.extend_from_slice(&chunk);
+    }
+}
+
+/// Returns the first event which matches or [`Error::Timeout`] if the owner of the clipboard
+/// doesn't answer.
+fn wait_for_event(conn: &RustConnection, matches: impl Fn(&Event) -> bool) -> Result<Event, Error> {
+    let deadline = Instant::now() + READ_TIMEOUT;
+
+    loop {
+        match conn.poll_for_event()? {
+            Some(event) if matches(&event) => return Ok(event),
+            Some(_) => {}
+            None if Instant::now() > deadline => return Err(Error::Timeout),
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+fn intern_atom(conn: &RustConnection, name: &str) -> Result<Atom, Error> {
+    Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+}
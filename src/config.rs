@@ -1,16 +1,63 @@
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+use crate::{
+    backend::Region,
+    export::{ExportFormat, PngCompression},
+    hooks::HookAction,
+    pipeline::PipelineStep,
+    recorder::encoder::AnimationFormat,
+    tray::hotkeys::HotkeyAction,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub x11: X11,
     pub wayland: Wayland,
+    pub recording: Recording,
+    pub timelapse: Timelapse,
+    pub history: History,
+    pub save: Save,
+    pub export: Export,
+    pub clipboard_offers: ClipboardOffers,
+
+    /// The HTTP endpoints screenshots can be uploaded to. The toolbar uses the first one.
+    #[serde(rename = "uploader")]
+    pub uploaders: Vec<Uploader>,
+
+    /// Commands which are run after a screenshot has been saved, copied or uploaded.
+    pub hooks: Vec<Hook>,
+
+    /// Global hotkeys of the tray, for example `"Super+Shift+Print" = "capture-screen"`.
+    pub hotkeys: BTreeMap<String, HotkeyAction>,
+
+    /// The image editor of the "Open in external editor" action, for example
+    /// `["gimp", "{file}"]`. `{file}` is replaced by the path of the screenshot.
+    pub external_editor: Option<Vec<String>>,
+
+    /// Copy the screenshot into the clipboard whenever the external editor saves it.
+    pub external_editor_copy_back: bool,
+
+    /// Keys of the editor which run a pipeline, for example `enter = ["save", "clipboard"]`.
+    pub pipelines: BTreeMap<String, Vec<PipelineStep>>,
 }
 
 impl Config {
+    /// Loads the config or returns the default config if the file doesn't exist or is invalid.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
+        if path.as_ref().exists() {
+            Self::load(path).unwrap_or_default()
+        } else {
+            Self::default()
+        }
+    }
+
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         match std::fs::read_to_string(&path) {
             Ok(content) => toml::from_str(&content).map_err(|e| {
@@ -29,46 +76,322 @@ impl Config {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct X11 {
     pub clipboard: Clipboard,
+
+    /// The command which prints the image in the clipboard. It's only used if flakeshot can't
+    /// read the clipboard itself. `{mime}` is replaced by `image/png`.
+    pub paste: Vec<String>,
 }
 
 impl Default for X11 {
     fn default() -> Self {
         Self {
-            clipboard: Clipboard::new(
+            clipboard: Clipboard::new("xclip", &["-selection", "clipboard", "-target", "{mime}"]),
+            paste: [
                 "xclip",
-                &["-selection", "clipboard", "-target", "image/png"],
-            ),
+                "-selection",
+                "clipboard",
+                "-target",
+                "{mime}",
+                "-o",
+            ]
+            .map(String::from)
+            .to_vec(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Wayland {
     pub clipboard: Clipboard,
+
+    /// The command which prints the image in the clipboard. It's only used if flakeshot can't
+    /// read the clipboard itself. `{mime}` is replaced by `image/png`.
+    pub paste: Vec<String>,
 }
 
 impl Default for Wayland {
     fn default() -> Self {
         Self {
             clipboard: Clipboard::new("wl-copy", &[]),
+            paste: ["wl-paste", "--type", "{mime}"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// The default settings of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recording {
+    pub format: AnimationFormat,
+
+    /// How many frames should be captured per second.
+    pub fps: u32,
+
+    /// The maximum length of a recording in seconds.
+    pub max_duration: u64,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::default(),
+            fps: 15,
+            max_duration: 30,
+        }
+    }
+}
+
+/// The default settings of a timelapse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timelapse {
+    /// The number of seconds between two screenshots.
+    pub interval: u64,
+
+    /// Don't store a screenshot if nothing changed since the previous one.
+    pub skip_unchanged: bool,
+
+    /// The directory where the screenshots are stored.
+    /// A new directory in the XDG data directory is used if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+
+    /// Stop after the given amount of screenshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<u32>,
+
+    /// Stop after the given amount of seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<u64>,
+
+    /// Only capture the output with the given name (for example `DP-1`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// Only capture the given region.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+}
+
+impl Default for Timelapse {
+    fn default() -> Self {
+        Self {
+            interval: 5,
+            skip_unchanged: true,
+            directory: None,
+            max_count: None,
+            max_duration: None,
+            output: None,
+            region: None,
+        }
+    }
+}
+
+/// The retention policy of the screenshot history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    /// Keep a copy of every confirmed screenshot.
+    pub enabled: bool,
+
+    /// Remove screenshots which are older than the given amount of days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+
+    /// Remove the oldest screenshots once the history exceeds the given amount of MiB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+
+    /// Keep at most the given amount of screenshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<usize>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age: Some(30),
+            max_size: Some(512),
+            max_count: None,
+        }
+    }
+}
+
+/// Where "Quick save" stores the screenshots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Save {
+    /// Defaults to `XDG_PICTURES_DIR/Screenshots`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+
+    /// The name of the file. The format is deduced from its extension.
+    ///
+    /// Supports the placeholders of `strftime`, `{output}`, `{width}`, `{height}` and `{n}`
+    /// (a number which is incremented until the name is free).
+    pub filename: String,
+}
+
+impl Default for Save {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            filename: "Screenshot_%Y-%m-%d_%H-%M-%S.png".to_string(),
+        }
+    }
+}
+
+/// The encoder options of the export formats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Export {
+    /// The format of screenshots in the clipboard or on stdout.
+    /// Files use the format of their extension.
+    pub format: ExportFormat,
+
+    pub png_compression: PngCompression,
+
+    /// 1 (worst) - 100 (best)
+    pub jpeg_quality: u8,
+
+    pub webp_lossless: bool,
+
+    /// 0 (worst) - 100 (best). Only used for lossy WebP.
+    pub webp_quality: u8,
+
+    /// 1 (slowest, smallest) - 10 (fastest, biggest)
+    pub avif_speed: u8,
+
+    /// 1 (worst) - 100 (best)
+    pub avif_quality: u8,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            png_compression: PngCompression::default(),
+            jpeg_quality: 90,
+            webp_lossless: true,
+            webp_quality: 80,
+            avif_speed: 6,
+            avif_quality: 80,
         }
     }
 }
 
+/// The representations of a screenshot in the clipboard in addition to the export format and
+/// PNG, so every application can pick the one it understands.
+///
+/// Only the export format is used if the clipboard is served by the clipboard manager command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardOffers {
+    /// `image/jpeg`
+    pub jpeg: bool,
+
+    /// `text/uri-list` with a temporary file, for file managers and terminals.
+    pub uri_list: bool,
+
+    /// `text/html` with the image as data URI, for rich text editors.
+    pub html: bool,
+}
+
+impl Default for ClipboardOffers {
+    fn default() -> Self {
+        Self {
+            jpeg: true,
+            uri_list: true,
+            html: false,
+        }
+    }
+}
+
+/// An HTTP endpoint like a self-hosted image host.
+///
+/// The image is sent as multipart form if `file_field` is set and as raw body otherwise.
+/// The URL of the uploaded image is extracted from the response with `json_pointer` or `regex`.
+/// The whole (trimmed) response is used if none of them is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Uploader {
+    #[serde(default = "method_default")]
+    pub method: String,
+
+    pub url: String,
+
+    /// The name of the multipart field of the image.
+    pub file_field: Option<String>,
+
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Additional fields of the multipart form.
+    #[serde(default)]
+    pub form: BTreeMap<String, String>,
+
+    /// For example `/data/link` for `{"data": {"link": "https://..."}}`.
+    pub json_pointer: Option<String>,
+
+    /// The first capture group (or the whole match if there's none) is used as URL.
+    pub regex: Option<String>,
+}
+
+fn method_default() -> String {
+    "POST".to_string()
+}
+
+/// A command which is run after a screenshot has been saved, copied or uploaded.
+/// See [`crate::hooks`] for the details which are passed to the command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hook {
+    /// The program and its arguments, for example `["optipng", "-quiet"]`.
+    pub command: Vec<String>,
+
+    /// The actions which trigger the hook. Every action triggers it if it's empty.
+    #[serde(default)]
+    pub on: Vec<HookAction>,
+
+    /// Pass the details as JSON document on stdin.
+    #[serde(default)]
+    pub stdin: bool,
+
+    /// Seconds after which the command is killed.
+    #[serde(default = "timeout_default")]
+    pub timeout: u64,
+}
+
+fn timeout_default() -> u64 {
+    30
+}
+
 /// Stores the clipboard maager command.
 ///
+/// The command is only used if `native` is disabled or if flakeshot can't own the clipboard
+/// itself (for example if the compositor doesn't support a data control protocol).
+///
+/// `{mime}` in the arguments is replaced by the mime type of the export format.
+///
 /// # Invariant
 /// It's always garanteed that the vector has at least one element (the command)!
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(remote = "Self")]
 pub struct Clipboard {
     pub cmd: String,
     pub args: Vec<String>,
+
+    /// Serve the clipboard by flakeshot itself instead of the command.
+    #[serde(default = "native_default")]
+    pub native: bool,
+}
+
+fn native_default() -> bool {
+    true
 }
 
 impl Clipboard {
@@ -78,6 +401,7 @@ impl Clipboard {
         Self {
             cmd: cmd.to_string(),
             args,
+            native: native_default(),
         }
     }
 
@@ -0,0 +1,169 @@
+//! Encodes screenshots with the encoder options of the config.
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use image::{
+    codecs::{
+        avif::AvifEncoder,
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+        qoi::QoiEncoder,
+        webp::WebPEncoder,
+    },
+    DynamicImage, ExtendedColorType, ImageEncoder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// All formats which a screenshot can be exported to with custom encoder options.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+    Qoi,
+}
+
+impl ExportFormat {
+    /// Returns the format which belongs to the extension of the path.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::Webp),
+            "avif" => Some(Self::Avif),
+            "qoi" => Some(Self::Qoi),
+            _ => None,
+        }
+    }
+
+    /// Returns the file extension (without the leading dot) of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+            Self::Qoi => "qoi",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+            Self::Avif => "image/avif",
+            Self::Qoi => "image/qoi",
+        }
+    }
+}
+
+/// How strong PNG files are compressed. Stronger compression takes longer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl From<PngCompression> for CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => Self::Fast,
+            PngCompression::Default => Self::Default,
+            PngCompression::Best => Self::Best,
+        }
+    }
+}
+
+/// Encodes the image in the given format and writes the result into `writer`.
+pub fn encode<W: Write>(
+    image: &DynamicImage,
+    format: ExportFormat,
+    options: &config::Export,
+    mut writer: W,
+) -> anyhow::Result<()> {
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    let result =
+        match format {
+            ExportFormat::Png => PngEncoder::new_with_quality(
+                writer,
+                options.png_compression.into(),
+                FilterType::Adaptive,
+            )
+            .write_image(&rgba, width, height, ExtendedColorType::Rgba8),
+            ExportFormat::Jpeg => {
+                // jpeg doesn't support transparency
+                let rgb = image.to_rgb8();
+
+                JpegEncoder::new_with_quality(writer, options.jpeg_quality.clamp(1, 100))
+                    .write_image(&rgb, width, height, ExtendedColorType::Rgb8)
+            }
+            ExportFormat::Webp if options.webp_lossless => WebPEncoder::new_lossless(writer)
+                .write_image(&rgba, width, height, ExtendedColorType::Rgba8),
+            ExportFormat::Webp => {
+                let encoded = webp::Encoder::from_rgba(&rgba, width, height)
+                    .encode(f32::from(options.webp_quality.min(100)));
+
+                return writer
+                    .write_all(&encoded)
+                    .context("Couldn't write the encoded image");
+            }
+            ExportFormat::Avif => AvifEncoder::new_with_speed_quality(
+                writer,
+                options.avif_speed.clamp(1, 10),
+                options.avif_quality.clamp(1, 100),
+            )
+            .write_image(&rgba, width, height, ExtendedColorType::Rgba8),
+            ExportFormat::Qoi => {
+                QoiEncoder::new(writer).write_image(&rgba, width, height, ExtendedColorType::Rgba8)
+            }
+        };
+
+    result.with_context(|| format!("Couldn't encode the image as {:?}", format))
+}
+
+/// Returns the image encoded in the given format.
+pub fn encode_to_vec(
+    image: &DynamicImage,
+    format: ExportFormat,
+    options: &config::Export,
+) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    encode(image, format, options, &mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Stores the image at the given path.
+///
+/// The format is deduced from the extension. Formats which don't have any encoder options
+/// (for example `bmp`) are stored with the default settings of their encoder.
+pub fn save(image: &DynamicImage, path: &Path, options: &config::Export) -> anyhow::Result<()> {
+    let Some(format) = ExportFormat::from_path(path) else {
+        return image
+            .save(path)
+            .with_context(|| format!("Couldn't save screenshot to {}", path.to_string_lossy()));
+    };
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Couldn't create {}", path.to_string_lossy()))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    encode(image, format, options, &mut writer)?;
+    writer
+        .flush()
+        .with_context(|| format!("Couldn't save screenshot to {}", path.to_string_lossy()))
+}
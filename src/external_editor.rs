@@ -0,0 +1,143 @@
+//! Opens screenshots in an external image editor like GIMP.
+//!
+//! The file can be watched while the editor is running, so the edited screenshot can be copied
+//! back into the clipboard whenever the editor saves it.
+use std::{
+    path::Path,
+    sync::Mutex,
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use tracing::warn;
+
+/// How often the file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The threads which watch files of running editors.
+static WATCHERS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Starts the editor with the file. `{file}` in the command is replaced by the path, the path is
+/// appended if the command doesn't contain `{file}`.
+///
+/// `on_saved` is called every time the editor saved the file until the editor exits.
+pub fn open<F>(command: &[String], path: &Path, on_saved: Option<F>) -> anyhow::Result<()>
+where
+    F: Fn(&Path) + Send + 'static,
+{
+    let (program, args) = command
+        .split_first()
+        .context("The command of the external editor is empty.")?;
+    let file = path.to_string_lossy();
+
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(args.iter().map(|arg| arg.replace("{file}", &file)));
+    if !args.iter().any(|arg| arg.contains("{file}")) {
+        cmd.arg(path);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Couldn't spawn '{}'", program))?;
+
+    let Some(on_saved) = on_saved else {
+        // reap the process once it's done so it doesn't stay around as a zombie
+        std::thread::spawn(move || child.wait());
+        return Ok(());
+    };
+
+    let path = path.to_path_buf();
+    let watcher = std::thread::spawn(move || {
+        let mut last_seen = get_modified(&path);
+        let mut last_handled = last_seen;
+
+        loop {
+            let exited = match child.try_wait() {
+                Ok(status) => status.is_some(),
+                Err(err) => {
+                    warn!("Couldn't wait for the external editor: {}", err);
+                    true
+                }
+            };
+
+            // the editor might write the file in several steps, so we wait until it stopped
+            let modified = get_modified(&path);
+            if modified == last_seen && modified != last_handled {
+                on_saved(&path);
+                last_handled = modified;
+            }
+            last_seen = modified;
+
+            if exited && last_seen == last_handled {
+                return;
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+
+    let mut watchers = WATCHERS.lock().unwrap();
+    watchers.retain(|watcher| !watcher.is_finished());
+    watchers.push(watcher);
+
+    Ok(())
+}
+
+/// Blocks until all watched editors have exited.
+///
+/// Should be called before the process exits, otherwise the changes aren't copied back.
+pub fn wait_for_watchers() {
+    let watchers = std::mem::take(&mut *WATCHERS.lock().unwrap());
+
+    for watcher in watchers {
+        let _ = watcher.join();
+    }
+}
+
+fn get_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_saves_of_the_editor_are_reported() {
+        let dir = std::env::temp_dir().join(format!("flakeshot-editor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("screenshot.png");
+        std::fs::write(&path, "original").unwrap();
+
+        let saves = Arc::new(AtomicUsize::new(0));
+        let counter = saves.clone();
+        let command = [
+            "sh".to_string(),
+            "-c".to_string(),
+            "sleep 1 && echo edited > \"$0\"".to_string(),
+            "{file}".to_string(),
+        ];
+
+        open(
+            &command,
+            &path,
+            Some(move |_: &Path| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .unwrap();
+        wait_for_watchers();
+
+        assert_eq!(saves.load(Ordering::SeqCst), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "edited\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
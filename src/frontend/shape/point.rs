@@ -1,4 +1,6 @@
-#[derive(Debug, Copy, Clone, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use super::point::Point;
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Rectangle {
     pub fst: Point,
     pub snd: Point,
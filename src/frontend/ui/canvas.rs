@@ -1,10 +1,102 @@
-use gtk4::cairo::{Context, Filter, Format, ImageSurface};
+use std::path::Path;
+
+use gtk4::cairo::{
+    Content, Context, Filter, Format, ImageSurface, PdfSurface, RecordingSurface, Rectangle,
+    SvgSurface,
+};
 use image::{DynamicImage, RgbaImage};
 
 pub trait CanvasDrawable {
     fn draw(&self, ctx: &Context, surface: &ImageSurface);
 }
 
+/// The formats which keep the drawables as vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorFormat {
+    Pdf,
+    Svg,
+}
+
+impl VectorFormat {
+    /// Returns the vector format of the extension of the path.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "pdf" => Some(Self::Pdf),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// A cropped screenshot whose drawables are recorded as vectors.
+#[derive(Debug)]
+pub struct VectorImage {
+    /// The screenshot without any drawables.
+    bitmap: ImageSurface,
+    drawables: RecordingSurface,
+}
+
+impl VectorImage {
+    /// Crops the capture and records the drawables on top of it as vectors.
+    pub fn new(
+        capture: &ImageSurface,
+        x: f64,
+        y: f64,
+        width: i32,
+        height: i32,
+        drawables: &[&dyn CanvasDrawable],
+    ) -> anyhow::Result<Self> {
+        let bitmap = ImageSurface::create(Format::ARgb32, width, height)?;
+        let bitmap_ctx = Context::new(&bitmap)?;
+        bitmap_ctx.set_source_surface(capture, -x, -y)?;
+        bitmap_ctx.paint()?;
+
+        let recording = RecordingSurface::create(
+            Content::ColorAlpha,
+            Some(Rectangle::new(0.0, 0.0, width as f64, height as f64)),
+        )?;
+        let recording_ctx = Context::new(&recording)?;
+        recording_ctx.translate(-x, -y);
+        for drawable in drawables {
+            // the drawables only use the surface to get the size of the canvas
+            drawable.draw(&recording_ctx, capture);
+        }
+
+        Ok(VectorImage {
+            bitmap,
+            drawables: recording,
+        })
+    }
+
+    /// Stores the image as PDF or SVG. The screenshot is embedded as bitmap and the drawables
+    /// stay vectors, so they are still crisp when the file is printed or scaled.
+    pub fn save(&self, path: &Path, format: VectorFormat) -> anyhow::Result<()> {
+        let width = self.bitmap.width() as f64;
+        let height = self.bitmap.height() as f64;
+
+        let surface = match format {
+            VectorFormat::Pdf => PdfSurface::new(width, height, path)?.as_ref().clone(),
+            VectorFormat::Svg => SvgSurface::new(width, height, Some(path))?.as_ref().clone(),
+        };
+
+        let ctx = Context::new(&surface)?;
+        ctx.set_source_surface(&self.bitmap, 0.0, 0.0)?;
+        ctx.paint()?;
+
+        // replaying a recording surface keeps its content as vectors
+        ctx.set_source_surface(&self.drawables, 0.0, 0.0)?;
+        ctx.paint()?;
+
+        drop(ctx);
+        surface.finish();
+        surface.status()?;
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct Canvas {
     surface: ImageSurface,
@@ -19,11 +111,22 @@ impl Canvas {
         })
     }
 
-    pub fn from_original(&self) -> Self {
-        Canvas {
-            surface: self.original.clone(),
+    pub fn from_original(&self) -> anyhow::Result<Self> {
+        // cloning a surface only clones the reference, drawing onto it would change the original
+        let surface = ImageSurface::create(Format::ARgb32, self.width(), self.height())?;
+        let ctx = Context::new(&surface)?;
+        ctx.set_source_surface(&self.original, 0.0, 0.0)?;
+        ctx.paint()?;
+
+        Ok(Canvas {
+            surface,
             original: self.original.clone(),
-        }
+        })
+    }
+
+    /// The screenshot without any drawables, as it has been stored by [`Self::save`].
+    pub fn original(&self) -> &ImageSurface {
+        &self.original
     }
 
     pub fn width(&self) -> i32 {
@@ -65,7 +168,8 @@ impl Canvas {
     ) -> anyhow::Result<()> {
         let ctx = Context::new(&self.surface)?;
 
-        let mut image_bytes = Vec::from(image.as_bytes());
+        // the image might have any color type or bit depth, cairo needs 8 bit RGBA
+        let mut image_bytes = image.to_rgba8().into_raw();
 
         // reverse RGB, keep Alpha
         for i in (0..image_bytes.len()).step_by(4) {
@@ -120,3 +224,56 @@ impl Canvas {
         Ok(DynamicImage::from(img))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    use super::*;
+
+    struct Line;
+
+    impl CanvasDrawable for Line {
+        fn draw(&self, ctx: &Context, _surface: &ImageSurface) {
+            ctx.move_to(10.0, 10.0);
+            ctx.line_to(90.0, 90.0);
+            ctx.stroke().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stamp_rgb_and_luma_images() {
+        let images: [DynamicImage; 2] = [
+            RgbImage::from_pixel(4, 3, Rgb([255, 0, 0])).into(),
+            GrayImage::from_pixel(4, 3, Luma([255])).into(),
+        ];
+
+        for image in images {
+            let canvas = Canvas::new(4, 3).unwrap();
+            canvas.stamp_image(0.0, 0.0, 4.0, 3.0, &image).unwrap();
+
+            let stamped = canvas.crop_to_image(0.0, 0.0, 4, 3).unwrap();
+            assert_eq!(stamped.to_rgba8(), image.to_rgba8());
+        }
+    }
+
+    #[test]
+    fn test_svg_keeps_drawables_as_vectors() {
+        // cairo skips painting a capture which is completely transparent
+        let capture = ImageSurface::create(Format::ARgb32, 100, 100).unwrap();
+        let ctx = Context::new(&capture).unwrap();
+        ctx.set_source_rgb(1.0, 1.0, 1.0);
+        ctx.paint().unwrap();
+        drop(ctx);
+
+        let image = VectorImage::new(&capture, 0.0, 0.0, 100, 100, &[&Line]).unwrap();
+
+        let path = std::env::temp_dir().join(format!("flakeshot-{}.svg", std::process::id()));
+        image.save(&path, VectorFormat::Svg).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(svg.contains("<image"), "{}", svg);
+        assert!(svg.contains("<path"), "{}", svg);
+    }
+}
@@ -1,7 +1,29 @@
 use gtk4::cairo::{Context, ImageSurface};
+use serde::{Deserialize, Serialize};
+
+use super::tool::crop::CropDrawable;
 
 pub trait Drawable: std::fmt::Debug {
     fn draw_active(&self, ctx: &Context, surface: &ImageSurface);
     fn draw_inactive(&self, ctx: &Context, surface: &ImageSurface);
     fn draw_final(&self, ctx: &Context, surface: &ImageSurface);
+
+    /// Returns the drawable in the form which is stored in projects.
+    fn to_serialized(&self) -> SerializedDrawable;
+}
+
+/// Every implementation of [`Drawable`], so they can be stored in a project and edited again
+/// after it has been reopened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SerializedDrawable {
+    Crop(CropDrawable),
+}
+
+impl SerializedDrawable {
+    pub fn into_drawable(self) -> Box<dyn Drawable> {
+        match self {
+            Self::Crop(drawable) => Box::new(drawable),
+        }
+    }
 }
@@ -1,5 +1,6 @@
 pub mod canvas;
 pub mod drawable;
+pub mod scene;
 pub mod tool;
 pub mod tool_manager;
 pub mod toolbar;
@@ -0,0 +1,254 @@
+//! Renders the capture and its drawables without any window or display, so it can be used by
+//! the editor as well as by tests and other tools.
+use gtk4::cairo::{Context, ImageSurface};
+use image::DynamicImage;
+
+use super::{
+    canvas::{Canvas, CanvasDrawable, VectorImage},
+    drawable::{Drawable, SerializedDrawable},
+};
+
+/// How a drawable is drawn onto the canvas.
+pub enum CanvasDrawableStrategy<'a> {
+    /// The drawable which is currently edited.
+    Active(&'a dyn Drawable),
+    Inactive(&'a dyn Drawable),
+    /// The drawable as it ends up in the screenshot.
+    Final(&'a dyn Drawable),
+}
+
+impl<'a> CanvasDrawable for CanvasDrawableStrategy<'a> {
+    fn draw(&self, ctx: &Context, surface: &ImageSurface) {
+        match self {
+            CanvasDrawableStrategy::Active(drawable) => drawable.draw_active(ctx, surface),
+            CanvasDrawableStrategy::Inactive(drawable) => drawable.draw_inactive(ctx, surface),
+            CanvasDrawableStrategy::Final(drawable) => drawable.draw_final(ctx, surface),
+        }
+    }
+}
+
+/// The capture and the drawables on top of it.
+///
+/// The active drawable (for example the one of the current tool) isn't part of the scene, it's
+/// passed to each render call instead.
+#[derive(Debug)]
+pub struct Scene {
+    canvas: Canvas,
+    drawables: Vec<Box<dyn Drawable>>,
+}
+
+impl Scene {
+    pub fn new(width: i32, height: i32) -> anyhow::Result<Self> {
+        Ok(Self {
+            canvas: Canvas::new(width, height)?,
+            drawables: vec![],
+        })
+    }
+
+    /// Creates a scene with the image as capture. The image may have any color type, it's
+    /// converted to 8 bit RGBA while it's stamped.
+    pub fn from_image(image: &DynamicImage) -> anyhow::Result<Self> {
+        let (width, height) = (image.width(), image.height());
+
+        let mut scene = Self::new(width as i32, height as i32)?;
+        scene
+            .canvas
+            .stamp_image(0.0, 0.0, width as f64, height as f64, image)?;
+        scene.canvas.save()?;
+
+        Ok(scene)
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    pub fn canvas_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
+
+    pub fn add_drawable(&mut self, drawable: Box<dyn Drawable>) {
+        self.drawables.push(drawable)
+    }
+
+    pub fn set_drawables(&mut self, drawables: Vec<Box<dyn Drawable>>) {
+        self.drawables = drawables;
+    }
+
+    pub fn serialized_drawables(&self) -> Vec<SerializedDrawable> {
+        self.drawables
+            .iter()
+            .map(|drawable| drawable.to_serialized())
+            .collect()
+    }
+
+    /// Renders the view of the editor onto the canvas: all drawables are inactive apart from
+    /// the active one.
+    pub fn render(&mut self, active: Option<&dyn Drawable>) -> anyhow::Result<()> {
+        self.canvas.clear()?;
+
+        for drawable in &self.drawables {
+            self.canvas
+                .render_drawable(&CanvasDrawableStrategy::Inactive(drawable.as_ref()));
+        }
+
+        if let Some(drawable) = active {
+            self.canvas
+                .render_drawable(&CanvasDrawableStrategy::Active(drawable));
+        }
+
+        Ok(())
+    }
+
+    /// Renders the screenshot into a new canvas. The canvas of the scene isn't changed.
+    pub fn render_final(&self, active: Option<&dyn Drawable>) -> anyhow::Result<Canvas> {
+        let mut canvas = self.canvas.from_original()?;
+
+        for strategy in self.final_strategies(active) {
+            canvas.render_drawable(&strategy);
+        }
+
+        Ok(canvas)
+    }
+
+    /// Renders the screenshot and crops it to the given region.
+    pub fn render_to_image(
+        &self,
+        active: Option<&dyn Drawable>,
+        x: f64,
+        y: f64,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<DynamicImage> {
+        self.render_final(active)?
+            .crop_to_image(x, y, width, height)
+    }
+
+    /// Like [`Self::render_to_image`], but the drawables are kept as vectors.
+    ///
+    /// Nothing is rendered until the returned function is called, so it's cheap if the vector
+    /// image might not be needed. The capture is shared and the drawables are copied, so later
+    /// changes of the scene don't end up in the image.
+    pub fn prepare_vector_image(
+        &self,
+        active: Option<&dyn Drawable>,
+        x: f64,
+        y: f64,
+        width: i32,
+        height: i32,
+    ) -> impl Fn() -> anyhow::Result<VectorImage> {
+        let capture = self.canvas.original().clone();
+        let drawables: Vec<Box<dyn Drawable>> = self
+            .drawables
+            .iter()
+            .map(|drawable| drawable.as_ref())
+            .chain(active)
+            .map(|drawable| drawable.to_serialized().into_drawable())
+            .collect();
+
+        move || {
+            let strategies: Vec<CanvasDrawableStrategy> = drawables
+                .iter()
+                .map(|drawable| CanvasDrawableStrategy::Final(drawable.as_ref()))
+                .collect();
+            let drawables: Vec<&dyn CanvasDrawable> = strategies
+                .iter()
+                .map(|strategy| strategy as &dyn CanvasDrawable)
+                .collect();
+
+            VectorImage::new(&capture, x, y, width, height, &drawables)
+        }
+    }
+
+    /// Returns the capture without any drawables.
+    pub fn capture(&self) -> anyhow::Result<DynamicImage> {
+        self.canvas.from_original()?.crop_to_image(
+            0.0,
+            0.0,
+            self.canvas.width() as u32,
+            self.canvas.height() as u32,
+        )
+    }
+
+    fn final_strategies<'a>(
+        &'a self,
+        active: Option<&'a dyn Drawable>,
+    ) -> Vec<CanvasDrawableStrategy<'a>> {
+        self.drawables
+            .iter()
+            .map(|drawable| drawable.as_ref())
+            .chain(active)
+            .map(CanvasDrawableStrategy::Final)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    use crate::frontend::{
+        shape::{point::Point, rectangle::Rectangle},
+        ui::tool::crop::CropDrawable,
+    };
+
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn crop() -> CropDrawable {
+        CropDrawable {
+            selection: Rectangle {
+                fst: Point { x: 20.0, y: 20.0 },
+                snd: Point { x: 80.0, y: 80.0 },
+            },
+        }
+    }
+
+    #[test]
+    fn test_crop_darkens_the_view_outside_of_the_selection() {
+        let mut scene = Scene::from_image(&RgbaImage::from_pixel(100, 100, WHITE).into()).unwrap();
+        scene.render(Some(&crop())).unwrap();
+
+        let view = scene
+            .canvas()
+            .crop_to_image(0.0, 0.0, 100, 100)
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(*view.get_pixel(50, 50), WHITE);
+
+        let outside = view.get_pixel(2, 2);
+        assert!((120..=135).contains(&outside.0[0]), "{:?}", outside);
+        assert_eq!(outside.0[3], 255);
+    }
+
+    #[test]
+    fn test_crop_isnt_part_of_the_screenshot() {
+        let mut scene = Scene::from_image(&RgbaImage::from_pixel(100, 100, WHITE).into()).unwrap();
+        scene.add_drawable(Box::new(crop()));
+        scene.render(None).unwrap();
+
+        let screenshot = scene
+            .render_to_image(Some(&crop()), 10.0, 10.0, 30, 20)
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(screenshot.dimensions(), (30, 20));
+        assert!(screenshot.pixels().all(|pixel| *pixel == WHITE));
+    }
+
+    #[test]
+    fn test_scene_from_rgb_image() {
+        let image = RgbImage::from_pixel(40, 30, Rgb([255, 255, 255])).into();
+        let scene = Scene::from_image(&image).unwrap();
+
+        let screenshot = scene
+            .render_to_image(None, 0.0, 0.0, 40, 30)
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(screenshot.dimensions(), (40, 30));
+        assert!(screenshot.pixels().all(|pixel| *pixel == WHITE));
+    }
+}
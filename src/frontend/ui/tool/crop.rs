@@ -1,8 +1,9 @@
 use gtk4::cairo::{Context, FillRule, ImageSurface};
+use serde::{Deserialize, Serialize};
 
 use crate::frontend::{
     shape::{point::Point, rectangle::Rectangle},
-    ui::drawable::Drawable,
+    ui::drawable::{Drawable, SerializedDrawable},
 };
 
 use super::{Tool, ToolCommand};
@@ -126,9 +127,13 @@ impl Tool for Crop {
     fn get_drawable(&self) -> &dyn Drawable {
         &self.drawable
     }
+
+    fn set_selection(&mut self, selection: Rectangle) {
+        self.drawable.selection = selection;
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CropDrawable {
     pub selection: Rectangle,
 }
@@ -235,4 +240,8 @@ impl Drawable for CropDrawable {
     fn draw_final(&self, _ctx: &Context, _surface: &ImageSurface) {
         // We won't draw anything to the final screenshot
     }
+
+    fn to_serialized(&self) -> SerializedDrawable {
+        SerializedDrawable::Crop(self.clone())
+    }
 }
@@ -24,6 +24,9 @@ pub trait Tool: std::fmt::Debug {
     fn handle_mouse_release(&mut self, point: Point) -> ToolCommand;
     fn get_drawable(&self) -> &dyn Drawable;
 
+    /// Restores the selection, for example after a project has been opened.
+    fn set_selection(&mut self, _selection: Rectangle) {}
+
     fn handle_mouse_event(&mut self, event: MouseEvent) -> ToolCommand {
         match event {
             MouseEvent::MouseMove(position) => self.handle_mouse_move(position),
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::frontend::shape::rectangle::Rectangle;
+
 use super::tool::{crop::Crop, Tool, ToolIdentifier};
 
 #[derive(Debug)]
@@ -52,6 +54,13 @@ impl ToolManager {
         None
     }
 
+    /// Passes the restored selection to all tools.
+    pub fn set_selection(&mut self, selection: Rectangle) {
+        for tool in self.tools.values_mut() {
+            tool.set_selection(selection);
+        }
+    }
+
     pub fn set_active_tool(&mut self, identifier: Option<ToolIdentifier>) {
         self.active_tool = identifier;
     }
@@ -1,28 +1,66 @@
+use std::{io::Cursor, rc::Rc};
+
+use anyhow::Context;
+use gtk::gdk;
+use gtk::glib;
 use gtk::prelude::ButtonExt;
 use gtk::prelude::OrientableExt;
 use gtk::prelude::ToggleButtonExt;
 use gtk::prelude::WidgetExt;
+use image::{DynamicImage, ImageFormat};
 use relm4::gtk::Align;
 use relm4::prelude::*;
 use relm4::SimpleComponent;
 
+use crate::{clipboard, get_xdg};
+
 use super::tool::ToolIdentifier;
 
+/// The file which is offered as `text/uri-list` while dragging the screenshot.
+const DRAG_FILENAME: &str = "drag.png";
+
+/// Renders the cropped screenshot which can be dragged out of the toolbar.
+/// It's only called once a drag starts.
+pub type DragImage = Rc<dyn Fn() -> Option<DynamicImage>>;
+
+pub struct ToolbarInit {
+    pub drag_image: DragImage,
+    /// Whether the selection can be recorded. That's only the case if it lies on the screen,
+    /// images which are opened in the editor can't be recorded.
+    pub recordable: bool,
+}
+
 #[derive(Debug)]
-pub struct Toolbar {}
+pub struct Toolbar {
+    recordable: bool,
+}
 
 #[derive(Debug, Clone)]
 pub enum ToolbarEvent {
     SaveAsFile,
+    QuickSave,
     SaveIntoClipboard,
+    Upload,
+    Pin,
+    ExternalEditor,
+    Record,
+
+    /// Stores the screenshot with its editable annotations (`.flakeshot`).
+    SaveProject,
     ToolSelect(ToolIdentifier),
+
+    /// Runs the pipeline of the config with the given key.
+    RunPipeline(String),
+
+    /// The user accepted the screenshot (Enter).
+    Confirm,
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for Toolbar {
     type Input = ();
     type Output = ToolbarEvent;
-    type Init = ();
+    type Init = ToolbarInit;
 
     view! {
         root = gtk::Box {
@@ -53,6 +91,13 @@ impl SimpleComponent for Toolbar {
 
             gtk::Separator {},
 
+            #[name(drag_handle)]
+            gtk::Image {
+                set_icon_name: Some("list-drag-handle-symbolic"),
+                set_tooltip_text: Some("Drag the screenshot into another application"),
+                add_css_class: "toolbar-button",
+            },
+
             gtk::Button {
                 set_icon_name: "paper",
                 set_tooltip_text: Some("Save to file"),
@@ -62,6 +107,24 @@ impl SimpleComponent for Toolbar {
                 },
             },
 
+            gtk::Button {
+                set_icon_name: "document-save-symbolic",
+                set_tooltip_text: Some("Quick save into the screenshot directory"),
+                add_css_class: "toolbar-button",
+                connect_clicked[sender] => move |_| {
+                    sender.output(ToolbarEvent::QuickSave).unwrap();
+                },
+            },
+
+            gtk::Button {
+                set_icon_name: "document-save-as-symbolic",
+                set_tooltip_text: Some("Save as project to edit the annotations later"),
+                add_css_class: "toolbar-button",
+                connect_clicked[sender] => move |_| {
+                    sender.output(ToolbarEvent::SaveProject).unwrap();
+                },
+            },
+
             gtk::Button {
                 set_icon_name: "copy",
                 add_css_class: "toolbar-button",
@@ -69,19 +132,94 @@ impl SimpleComponent for Toolbar {
                 connect_clicked[sender] => move |_| {
                     sender.output(ToolbarEvent::SaveIntoClipboard).unwrap();
                 },
+            },
+
+            gtk::Button {
+                set_icon_name: "send-to-symbolic",
+                add_css_class: "toolbar-button",
+                set_tooltip_text: Some("Upload and copy the URL to clipboard"),
+                connect_clicked[sender] => move |_| {
+                    sender.output(ToolbarEvent::Upload).unwrap();
+                },
+            },
+
+            gtk::Button {
+                set_icon_name: "view-pin-symbolic",
+                add_css_class: "toolbar-button",
+                set_tooltip_text: Some("Pin to the screen"),
+                connect_clicked[sender] => move |_| {
+                    sender.output(ToolbarEvent::Pin).unwrap();
+                },
+            },
+
+            gtk::Button {
+                set_icon_name: "applications-graphics-symbolic",
+                add_css_class: "toolbar-button",
+                set_tooltip_text: Some("Open in external editor"),
+                connect_clicked[sender] => move |_| {
+                    sender.output(ToolbarEvent::ExternalEditor).unwrap();
+                },
+            },
+
+            gtk::Button {
+                set_icon_name: "media-record-symbolic",
+                add_css_class: "toolbar-button",
+                set_tooltip_text: Some("Record the selected region"),
+                set_visible: model.recordable,
+                connect_clicked[sender] => move |_| {
+                    sender.output(ToolbarEvent::Record).unwrap();
+                },
             }
         }
     }
 
     fn init(
-        _init: Self::Init,
+        init: Self::Init,
         root: Self::Root,
         sender: relm4::prelude::ComponentSender<Self>,
     ) -> relm4::prelude::ComponentParts<Self> {
-        let model = Toolbar {};
+        let model = Toolbar {
+            recordable: init.recordable,
+        };
+        let drag_image = init.drag_image;
 
         let widgets = view_output!();
 
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::COPY);
+        drag_source.connect_prepare(move |_, _, _| {
+            let image = drag_image()?;
+
+            match get_drag_content(&image) {
+                Ok(content) => Some(content),
+                Err(err) => {
+                    tracing::warn!("Couldn't drag the screenshot: {:#}", err);
+                    None
+                }
+            }
+        });
+        widgets.drag_handle.add_controller(drag_source);
+
         ComponentParts { model, widgets }
     }
 }
+
+/// Offers the image as `image/png` and as temporary file (`text/uri-list`).
+fn get_drag_content(image: &DynamicImage) -> anyhow::Result<gdk::ContentProvider> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .context("Couldn't encode the screenshot")?;
+
+    let path = get_xdg()
+        .place_runtime_file(DRAG_FILENAME)
+        .context("Couldn't create the temporary file of the drag")?;
+    std::fs::write(&path, &png)
+        .with_context(|| format!("Couldn't write {}", path.to_string_lossy()))?;
+    let uri_list = format!("{}\r\n", clipboard::file_uri(&path));
+
+    Ok(gdk::ContentProvider::new_union(&[
+        gdk::ContentProvider::for_bytes("image/png", &glib::Bytes::from_owned(png)),
+        gdk::ContentProvider::for_bytes("text/uri-list", &glib::Bytes::from_owned(uri_list)),
+    ]))
+}
@@ -1,17 +1,22 @@
 use std::{
-    io::{Cursor, Write},
-    process::Stdio,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use anyhow::Context;
+use chrono::Local;
 use derive_where::derive_where;
-use gtk4::cairo::{self, ImageSurface};
-use image::{DynamicImage, GenericImageView, ImageFormat};
+use gtk4::cairo::ImageSurface;
+use image::DynamicImage;
 use notify_rust::Urgency;
 use relm4::ComponentSender;
 
 use crate::{
+    autosave,
+    backend::Region,
+    cli::GuiArgs,
     config::Config,
+    export, external_editor,
     frontend::{
         shape::rectangle::Rectangle,
         window::{
@@ -21,11 +26,19 @@ use crate::{
             screenshot_window::MouseEvent,
         },
     },
+    get_xdg,
+    history::{self, Destination, Metadata},
+    hooks, pipeline,
+    project::{self, Project},
+    recent, save,
+    tray::dbus,
+    upload,
 };
 
 use super::{
-    canvas::{Canvas, CanvasDrawable},
-    drawable::Drawable,
+    canvas::{VectorFormat, VectorImage},
+    drawable::{Drawable, SerializedDrawable},
+    scene::Scene,
     tool::ToolCommand,
     tool_manager::ToolManager,
     toolbar::ToolbarEvent,
@@ -33,34 +46,32 @@ use super::{
 
 type RenderObserver = dyn Fn(&UiManager);
 
-enum CanvasDrawableStrategy<'a> {
-    Active(&'a dyn Drawable),
-    Inactive(&'a dyn Drawable),
-    Final(&'a dyn Drawable),
-}
+/// Set once the screenshot has been written to stdout (`gui --stdout`).
+static CONFIRMED: AtomicBool = AtomicBool::new(false);
 
-impl<'a> CanvasDrawable for CanvasDrawableStrategy<'a> {
-    fn draw(&self, ctx: &cairo::Context, surface: &ImageSurface) {
-        match self {
-            CanvasDrawableStrategy::Active(drawable) => drawable.draw_active(ctx, surface),
-            CanvasDrawableStrategy::Inactive(drawable) => drawable.draw_inactive(ctx, surface),
-            CanvasDrawableStrategy::Final(drawable) => drawable.draw_final(ctx, surface),
-        }
-    }
+/// Returns `true` if the user confirmed the screenshot in `gui --stdout` mode.
+pub fn is_confirmed() -> bool {
+    CONFIRMED.load(Ordering::Relaxed)
 }
 
 #[derive_where(Debug)]
 pub struct UiManager {
     tool_manager: ToolManager,
-    canvas: Canvas,
+    scene: Scene,
     selection: Rectangle,
-    drawables: Vec<Box<dyn Drawable>>,
     sender: ComponentSender<AppModel>,
 
     #[derive_where(skip(Debug))]
     render_observer: Vec<Box<RenderObserver>>,
 
+    /// The name and geometry of each output.
+    outputs: Vec<(String, Region)>,
+
     config: Config,
+    options: GuiArgs,
+
+    /// Whether the state changed since the last autosave.
+    changed: bool,
 }
 
 impl UiManager {
@@ -69,15 +80,18 @@ impl UiManager {
         total_height: i32,
         sender: ComponentSender<AppModel>,
         config: Config,
+        options: GuiArgs,
     ) -> Self {
         UiManager {
             tool_manager: ToolManager::new(),
-            canvas: Canvas::new(total_width, total_height).expect("Couldn't create canvas."),
+            scene: Scene::new(total_width, total_height).expect("Couldn't create canvas."),
             selection: Rectangle::with_size(total_width as f64, total_height as f64),
-            drawables: vec![],
             render_observer: vec![],
+            outputs: vec![],
             sender,
             config,
+            options,
+            changed: false,
         }
     }
 
@@ -89,7 +103,10 @@ impl UiManager {
     }
 
     pub fn persist_canvas(&mut self) {
-        self.canvas.save().expect("Couldn't persist canvas.");
+        self.scene
+            .canvas_mut()
+            .save()
+            .expect("Couldn't persist canvas.");
     }
 
     pub fn stamp_image(
@@ -100,47 +117,135 @@ impl UiManager {
         height: f64,
         image: &DynamicImage,
     ) -> anyhow::Result<()> {
-        self.canvas.stamp_image(x, y, width, height, image)?;
+        self.scene
+            .canvas()
+            .stamp_image(x, y, width, height, image)?;
         self.notify_render_observer();
 
         Ok(())
     }
 
     pub fn crop(&self, x: f64, y: f64, width: i32, height: i32) -> anyhow::Result<ImageSurface> {
-        self.canvas.crop(x, y, width, height)
+        self.scene.canvas().crop(x, y, width, height)
+    }
+
+    pub fn add_output(&mut self, name: String, geometry: Region) {
+        self.outputs.push((name, geometry));
     }
 
     pub fn add_drawable(&mut self, drawable: Box<dyn Drawable>) {
-        self.drawables.push(drawable)
+        self.scene.add_drawable(drawable)
     }
 
     pub fn handle_tool_event(&mut self, event: ToolbarEvent) {
         match event {
+            ToolbarEvent::SaveAsFile
+            | ToolbarEvent::QuickSave
+            | ToolbarEvent::SaveIntoClipboard
+            | ToolbarEvent::Upload
+            | ToolbarEvent::RunPipeline(_)
+            | ToolbarEvent::Confirm
+                if self.options.stdout =>
+            {
+                self.write_to_stdout()
+            }
+            ToolbarEvent::Confirm => {}
+            ToolbarEvent::SaveAsFile if self.options.quick_save => self.quick_save(),
             ToolbarEvent::SaveAsFile => self.save_to_file(),
+            ToolbarEvent::QuickSave => self.quick_save(),
             ToolbarEvent::SaveIntoClipboard => {
                 match self.save_to_clipboard() {
                     Ok(()) => self.sender.spawn_oneshot_command(|| Command::Close),
                     Err(err) => notify(
                         &self.sender,
                         Notification {
-                            msg: err.to_string(),
+                            msg: format!("{:#}", err),
                             urgency: Urgency::Critical,
                         },
                     ),
                 };
             }
+            ToolbarEvent::Upload => self.upload(),
+            ToolbarEvent::ExternalEditor => self.open_in_external_editor(),
+            ToolbarEvent::SaveProject => self.save_project(),
+            ToolbarEvent::Pin => {
+                let image = self.get_crop_image().to_rgba8();
+                self.sender
+                    .spawn_oneshot_command(move || Command::Pin(image));
+            }
+            ToolbarEvent::Record => match self.get_selection_region() {
+                Some(region) => self
+                    .sender
+                    .spawn_oneshot_command(move || Command::StartRecording(region)),
+                None => notify(
+                    &self.sender,
+                    Notification {
+                        msg: "Please select the region which should be recorded.".to_string(),
+                        urgency: Urgency::Normal,
+                    },
+                ),
+            },
             ToolbarEvent::ToolSelect(tool_identifier) => {
                 self.tool_manager.set_active_tool(Some(tool_identifier))
             }
+            ToolbarEvent::RunPipeline(key) => self.run_pipeline(&key),
         }
     }
 
     pub fn handle_mouse_event(&mut self, event: MouseEvent) {
+        let released = matches!(event, MouseEvent::MouseRelease { .. });
+
         if let Some(tool) = self.tool_manager.active_tool_mut() {
             let cmd = tool.handle_mouse_event(event);
             self.handle_tool_command(cmd);
             self.render();
         }
+
+        // a finished drag is the only way to change anything at the moment
+        if released {
+            self.changed = true;
+        }
+    }
+
+    /// Restores the selection and the drawables of a project. Its capture has to be stamped
+    /// before.
+    pub fn restore_project(&mut self, selection: Rectangle, drawables: Vec<SerializedDrawable>) {
+        self.set_crop_area(selection);
+        self.tool_manager.set_selection(selection);
+        self.scene.set_drawables(
+            drawables
+                .into_iter()
+                .map(SerializedDrawable::into_drawable)
+                .collect(),
+        );
+
+        self.render();
+        self.changed = true;
+    }
+
+    /// Stores a snapshot of the state in the background if it changed since the last one.
+    pub fn autosave(&mut self) {
+        if !self.changed {
+            return;
+        }
+        self.changed = false;
+
+        match self.to_project() {
+            Ok(project) => {
+                let session = autosave::session();
+                self.sender.spawn_command(move |_| {
+                    if let Err(err) = autosave::save(&project, session) {
+                        tracing::warn!("Couldn't autosave the editor: {:#}", err);
+                    }
+                });
+            }
+            Err(err) => tracing::warn!("Couldn't autosave the editor: {:#}", err),
+        }
+    }
+
+    /// Renders the image which can be dragged out of the toolbars.
+    pub fn get_drag_image(&self) -> Option<DynamicImage> {
+        self.get_selection_region().map(|_| self.get_crop_image())
     }
 
     fn set_crop_area(&mut self, rectangle: Rectangle) {
@@ -154,18 +259,20 @@ impl UiManager {
         }
     }
 
-    fn render(&mut self) {
-        self.canvas.clear().expect("Couldn't clear canvas.");
-
-        for drawable in &self.drawables {
-            self.canvas
-                .render_drawable(&CanvasDrawableStrategy::Inactive(drawable.as_ref()));
-        }
+    /// Returns the drawable of the active tool.
+    fn active_drawable(&self) -> Option<&dyn Drawable> {
+        self.tool_manager
+            .active_tool()
+            .map(|tool| tool.get_drawable())
+    }
 
-        if let Some(tool) = self.tool_manager.active_tool() {
-            self.canvas
-                .render_drawable(&CanvasDrawableStrategy::Active(tool.get_drawable()));
-        }
+    fn render(&mut self) {
+        // `active_drawable` would borrow all of `self` while the scene is borrowed mutably
+        let active = self
+            .tool_manager
+            .active_tool()
+            .map(|tool| tool.get_drawable());
+        self.scene.render(active).expect("Couldn't render canvas.");
 
         self.notify_render_observer();
     }
@@ -175,29 +282,34 @@ impl UiManager {
             observer(self);
         }
     }
+}
 
-    fn render_screenshot(&self) -> Canvas {
-        let mut canvas = self.canvas.from_original();
+impl UiManager {
+    /// Returns the current selection or `None` if the selection is empty.
+    fn get_selection_region(&self) -> Option<Region> {
+        let (fst, snd) = self.selection.get_points_clockwise();
 
-        for drawable in &self.drawables {
-            canvas.render_drawable(&CanvasDrawableStrategy::Final(drawable.as_ref()));
-        }
+        let width = (snd.x - fst.x).floor() as u32;
+        let height = (snd.y - fst.y).floor() as u32;
 
-        if let Some(tool) = self.tool_manager.active_tool() {
-            canvas.render_drawable(&CanvasDrawableStrategy::Final(tool.get_drawable()));
+        if width == 0 || height == 0 {
+            return None;
         }
 
-        canvas
+        Some(Region {
+            x: fst.x.floor() as i32,
+            y: fst.y.floor() as i32,
+            width,
+            height,
+        })
     }
-}
 
-impl UiManager {
     fn get_crop_image(&self) -> DynamicImage {
-        let canvas = self.render_screenshot();
         let Rectangle { fst, snd } = self.selection;
 
-        canvas
-            .crop_to_image(
+        self.scene
+            .render_to_image(
+                self.active_drawable(),
                 fst.x,
                 fst.y,
                 std::cmp::max(0, (snd.x - fst.x).floor() as u32),
@@ -206,25 +318,111 @@ impl UiManager {
             .expect("Couldn't crop canvas.")
     }
 
+    /// Like [`Self::get_crop_image`], but the drawables are kept as vectors. It's only rendered
+    /// once the returned function is called.
+    fn get_crop_vector_image(&self) -> impl Fn() -> anyhow::Result<VectorImage> {
+        let Rectangle { fst, snd } = self.selection;
+
+        self.scene.prepare_vector_image(
+            self.active_drawable(),
+            fst.x,
+            fst.y,
+            std::cmp::max(0, (snd.x - fst.x).floor() as i32),
+            std::cmp::max(0, (snd.y - fst.y).floor() as i32),
+        )
+    }
+
+    /// Returns the names of the outputs which overlap with the selection.
+    fn get_selected_outputs(&self) -> Vec<String> {
+        let Some(selection) = self.get_selection_region() else {
+            return vec![];
+        };
+
+        self.outputs
+            .iter()
+            .filter(|(_, output)| {
+                selection.x < output.x + output.width as i32
+                    && output.x < selection.x + selection.width as i32
+                    && selection.y < output.y + output.height as i32
+                    && output.y < selection.y + selection.height as i32
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Returns a function which stores the exported screenshot in the history and runs the
+    /// hooks of the config.
+    fn on_exported(&self) -> impl Fn(&DynamicImage, Destination) {
+        let history_enabled = self.config.history.enabled;
+        let hooks = self.config.hooks.clone();
+        let outputs = self.get_selected_outputs();
+        let region = self.get_selection_region();
+        let sender = self.sender.command_sender().clone();
+
+        move |img, destination| {
+            let event = hooks::Event::new(&destination, img.width(), img.height(), outputs.clone());
+            let sender = sender.clone();
+            hooks::run(&hooks, &event, move |msg| {
+                sender.emit(Command::Notify(Notification {
+                    msg,
+                    urgency: Urgency::Critical,
+                }))
+            });
+
+            if !history_enabled {
+                return;
+            }
+
+            let metadata = Metadata {
+                time: Local::now(),
+                outputs: outputs.clone(),
+                region,
+                destination,
+            };
+
+            // encoding the screenshot would block the ui
+            history::add_in_background(img.clone(), metadata);
+        }
+    }
+
     fn save_to_file(&self) {
         let img = self.get_crop_image();
+        let vector_img = self.get_crop_vector_image();
+        let on_exported = self.on_exported();
+        let export_options = self.config.export.clone();
 
         let sender = self.sender.clone();
         FileChooser::open(move |file| {
             if let Some(path) = file {
-                match img.save(&path) {
-                    Ok(()) => notify(
-                        &sender,
-                        Notification {
-                            msg: format!("Screenshot save to {}", path.to_string_lossy()),
-                            urgency: Urgency::Low,
-                        },
-                    ),
+                // PDF and SVG keep the drawables as vectors, everything else is flattened
+                let result = match VectorFormat::from_path(&path) {
+                    Some(format) => {
+                        vector_img().and_then(|vector_img| vector_img.save(&path, format))
+                    }
+                    None => export::save(&img, &path, &export_options),
+                };
+
+                match result {
+                    Ok(()) => {
+                        dbus::emit_screenshot_saved(&path);
+                        if let Err(err) = recent::add(&path) {
+                            tracing::warn!("{:#}", err);
+                        }
+                        on_exported(&img, Destination::File(path.clone()));
+
+                        notify(
+                            &sender,
+                            Notification {
+                                msg: format!("Screenshot save to {}", path.to_string_lossy()),
+                                urgency: Urgency::Low,
+                            },
+                        )
+                    }
                     Err(err) => notify(
                         &sender,
                         Notification {
                             msg: format!(
-                                "Couldn't save screenshot to {}: {}",
+                                "Couldn't save screenshot to {}: {:#}",
                                 path.to_string_lossy(),
                                 err
                             ),
@@ -236,52 +434,38 @@ impl UiManager {
         });
     }
 
-    fn save_to_clipboard(&self) -> anyhow::Result<()> {
+    fn quick_save(&self) {
         let img = self.get_crop_image();
+        let output = self.get_selected_outputs().join("+");
+
+        match save::quick_save(&self.config, &img, &output) {
+            Ok(path) => {
+                self.on_exported()(&img, Destination::File(path.clone()));
+
+                notify(
+                    &self.sender,
+                    Notification {
+                        msg: format!("Screenshot save to {}", path.to_string_lossy()),
+                        urgency: Urgency::Low,
+                    },
+                );
+                self.sender.spawn_oneshot_command(|| Command::Close);
+            }
+            Err(err) => notify(
+                &self.sender,
+                Notification {
+                    msg: format!("{:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            ),
+        }
+    }
 
-        let mut child = {
-            let (clip_man, args) = if crate::backend::is_wayland() {
-                (
-                    self.config.wayland.clipboard.cmd(),
-                    self.config.wayland.clipboard.args(),
-                )
-            } else {
-                (
-                    self.config.x11.clipboard.cmd(),
-                    self.config.x11.clipboard.args(),
-                )
-            };
-
-            std::process::Command::new(clip_man)
-                .args(args)
-                .stdin(Stdio::piped())
-                .spawn()
-                .unwrap_or_else(|err| {
-                    panic!(
-                        "Coulnd't spawn '{}'. Is it a typo or did you really install it?\n{}",
-                        &clip_man, err
-                    )
-                })
-        };
-
-        let mut image_bytes: Vec<u8> = {
-            let dim = img.dimensions();
-            Vec::with_capacity((dim.0 * dim.1) as usize)
-        };
-
-        img.write_to(&mut Cursor::new(&mut image_bytes), ImageFormat::Png)
-            .context("Couldn't write image to stdin of clipboard process")?;
+    fn save_to_clipboard(&self) -> anyhow::Result<()> {
+        let img = self.get_crop_image();
 
-        let child_stdin = child
-            .stdin
-            .as_mut()
-            .context("Couldn't get stdin of clipboard-process")?;
-        child_stdin
-            .write_all(&image_bytes)
-            .context("Couldn't write image bytes into clipboard")?;
-        child_stdin
-            .flush()
-            .context("Couldn't flush image to clipboard.")?;
+        crate::clipboard::copy_image(&self.config, &img)?;
+        self.on_exported()(&img, Destination::Clipboard);
 
         notify(
             &self.sender,
@@ -293,6 +477,203 @@ impl UiManager {
 
         Ok(())
     }
+
+    fn write_to_stdout(&self) {
+        let img = self.get_crop_image();
+
+        match crate::write_to_stdout(&img, &self.config.export) {
+            Ok(()) => {
+                CONFIRMED.store(true, Ordering::Relaxed);
+                self.sender.spawn_oneshot_command(|| Command::Close);
+            }
+            Err(err) => notify(
+                &self.sender,
+                Notification {
+                    msg: format!("{:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            ),
+        }
+    }
+
+    fn run_pipeline(&self, key: &str) {
+        let Some(steps) = self.config.pipelines.get(key).cloned() else {
+            return;
+        };
+
+        let img = self.get_crop_image();
+        let output = self.get_selected_outputs().join("+");
+        let on_exported = self.on_exported();
+        let config = self.config.clone();
+        let key = key.to_string();
+
+        // `run` waits for its command, so the pipeline mustn't block the ui
+        self.sender.spawn_oneshot_command(move || {
+            match pipeline::run(&steps, &config, &img, &output, on_exported) {
+                Ok(()) => Command::Close,
+                Err(err) => Command::Notify(Notification {
+                    msg: format!("The pipeline '{}' failed: {:#}", key, err),
+                    urgency: Urgency::Critical,
+                }),
+            }
+        });
+    }
+
+    fn to_project(&self) -> anyhow::Result<Project> {
+        Ok(Project {
+            capture: self.scene.capture()?.to_rgba8(),
+            selection: self.selection,
+            drawables: self.scene.serialized_drawables(),
+        })
+    }
+
+    /// Stores the capture and the drawables in a project, so they can be edited later.
+    fn save_project(&self) {
+        let project = match self.to_project() {
+            Ok(project) => project,
+            Err(err) => {
+                notify(
+                    &self.sender,
+                    Notification {
+                        msg: format!("Couldn't create the project: {:#}", err),
+                        urgency: Urgency::Critical,
+                    },
+                );
+                return;
+            }
+        };
+
+        let sender = self.sender.clone();
+        FileChooser::open(move |file| {
+            let Some(mut path) = file else {
+                return;
+            };
+
+            if !project::is_project(&path) {
+                path.as_mut_os_string()
+                    .push(format!(".{}", project::EXTENSION));
+            }
+
+            let notification = match project.save(&path) {
+                Ok(()) => Notification {
+                    msg: format!("Project saved to {}", path.to_string_lossy()),
+                    urgency: Urgency::Low,
+                },
+                Err(err) => Notification {
+                    msg: format!("Couldn't save the project: {:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            };
+            notify(&sender, notification);
+        });
+    }
+
+    fn open_in_external_editor(&self) {
+        let Some(command) = &self.config.external_editor else {
+            notify(
+                &self.sender,
+                Notification {
+                    msg: "There's no external editor. Please set `external_editor` in the config."
+                        .to_string(),
+                    urgency: Urgency::Normal,
+                },
+            );
+            return;
+        };
+
+        let img = self.get_crop_image();
+        let config = self.config.clone();
+        let sender = self.sender.command_sender().clone();
+        let copy_back = self
+            .config
+            .external_editor_copy_back
+            .then_some(move |path: &Path| {
+                let result = image::open(path)
+                    .with_context(|| format!("Couldn't open {}", path.to_string_lossy()))
+                    .and_then(|image| crate::clipboard::copy_image(&config, &image));
+
+                sender.emit(Command::Notify(match result {
+                    Ok(()) => Notification {
+                        msg: "Edited screenshot saved to clipboard.".to_string(),
+                        urgency: Urgency::Low,
+                    },
+                    Err(err) => Notification {
+                        msg: format!("Couldn't copy the edited screenshot: {:#}", err),
+                        urgency: Urgency::Critical,
+                    },
+                }));
+            });
+
+        let result = get_external_editor_path().and_then(|path| {
+            export::save(&img, &path, &self.config.export)?;
+            external_editor::open(command, &path, copy_back)
+        });
+
+        match result {
+            Ok(()) => self.sender.spawn_oneshot_command(|| Command::Close),
+            Err(err) => notify(
+                &self.sender,
+                Notification {
+                    msg: format!("Couldn't open the external editor: {:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            ),
+        }
+    }
+
+    fn upload(&self) {
+        let Some(uploader) = self.config.uploaders.first().cloned() else {
+            notify(
+                &self.sender,
+                Notification {
+                    msg: "There's no uploader. Please add an [[uploader]] to the config."
+                        .to_string(),
+                    urgency: Urgency::Normal,
+                },
+            );
+            return;
+        };
+
+        let img = self.get_crop_image();
+        let on_exported = self.on_exported();
+        let config = self.config.clone();
+
+        self.sender.command_sender().emit(Command::Uploading);
+        self.sender.spawn_oneshot_command(move || {
+            let result = upload::upload_image(&uploader, &img, &config.export).and_then(|url| {
+                crate::clipboard::copy_text(&config, &url)?;
+                Ok(url)
+            });
+
+            Command::UploadFinished(match result {
+                Ok(url) => {
+                    on_exported(&img, Destination::Upload(url.clone()));
+
+                    Notification {
+                        msg: format!("Screenshot uploaded to {} (copied to clipboard)", url),
+                        urgency: Urgency::Low,
+                    }
+                }
+                Err(err) => Notification {
+                    msg: format!("Couldn't upload the screenshot: {:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            })
+        });
+    }
+}
+
+/// Returns a new temporary file for the external editor, so several screenshots can be edited
+/// at the same time.
+fn get_external_editor_path() -> anyhow::Result<PathBuf> {
+    let filename = format!(
+        "external-editor/{}.png",
+        Local::now().format("%Y%m%d-%H%M%S%.3f")
+    );
+
+    get_xdg()
+        .place_runtime_file(filename)
+        .context("Couldn't create the file for the external editor")
 }
 
 fn notify(app_model_sender: &ComponentSender<AppModel>, msg: Notification) {
@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use gtk::{cairo::ImageSurface, glib, prelude::EventControllerExt};
+use relm4::{
+    abstractions::drawing::DrawHandler,
+    gtk::{
+        self,
+        prelude::{GtkWindowExt, WidgetExt},
+    },
+    Component, ComponentController, ComponentParts, ComponentSender, Controller, SimpleComponent,
+};
+
+use crate::frontend::{
+    shape::point::Point,
+    ui::toolbar::{DragImage, Toolbar, ToolbarInit},
+};
+
+use super::{
+    main_window::{AppModel, Command},
+    screenshot_window::{MouseEvent, ScreenshotWindowInput, ScreenshotWindowOutput},
+};
+
+/// The maximum initial width and height of the window. Bigger images can be scrolled.
+const MAX_INITIAL_SIZE: (i32, i32) = (1280, 800);
+
+/// Shows an existing image in a normal window instead of the fullscreen overlays.
+pub struct EditorWindowInit {
+    pub title: String,
+    pub width: i32,
+    pub height: i32,
+    pub parent_sender: Rc<relm4::ComponentSender<AppModel>>,
+    pub drag_image: DragImage,
+}
+
+#[derive(Debug)]
+pub struct EditorWindowModel {
+    draw_handler: DrawHandler,
+    surface: Option<ImageSurface>,
+    toolbar: Controller<Toolbar>,
+}
+
+impl EditorWindowModel {
+    fn draw(&mut self, surface: Option<ImageSurface>) {
+        let ctx = self.draw_handler.get_context();
+
+        if let Some(surface) = surface {
+            self.surface = Some(surface);
+        }
+
+        if let Some(surface) = &self.surface {
+            ctx.set_source_surface(surface, 0.0, 0.0)
+                .expect("Couldn't set source surface.");
+
+            ctx.paint().expect("Couldn't paint.");
+        }
+    }
+}
+
+impl SimpleComponent for EditorWindowModel {
+    type Input = ScreenshotWindowInput;
+    type Output = ScreenshotWindowOutput;
+    type Init = EditorWindowInit;
+    type Root = gtk::Window;
+    type Widgets = ();
+
+    fn init_root() -> Self::Root {
+        gtk::Window::new()
+    }
+
+    fn init(
+        payload: EditorWindowInit,
+        window: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let toolbar = Toolbar::builder()
+            .launch(ToolbarInit {
+                drag_image: payload.drag_image,
+                // the image isn't on the screen
+                recordable: false,
+            })
+            .forward(sender.input_sender(), |event| {
+                ScreenshotWindowInput::ToolbarEvent(event)
+            });
+
+        let model = EditorWindowModel {
+            draw_handler: DrawHandler::new(),
+            surface: None,
+            toolbar,
+        };
+
+        window.set_title(Some(&payload.title));
+        window.set_default_size(
+            payload.width.min(MAX_INITIAL_SIZE.0),
+            payload.height.min(MAX_INITIAL_SIZE.1),
+        );
+
+        let close_sender = payload.parent_sender.clone();
+        window.connect_close_request(move |_| {
+            let _ = close_sender.command_sender().send(Command::Close);
+            glib::Propagation::Proceed
+        });
+
+        let realize_sender = sender.clone();
+        window.connect_realize(move |_| {
+            // make sure window is finished rendering before first draw
+            let s = realize_sender.clone();
+            glib::idle_add_local_once(move || {
+                s.input(ScreenshotWindowInput::Redraw);
+            });
+        });
+
+        // the toolbar stays at the bottom of the window while the image is scrolled
+        let overlay = gtk::Overlay::new();
+        window.set_child(Some(&overlay));
+
+        let drawing_area = model.draw_handler.drawing_area();
+        drawing_area.set_size_request(payload.width, payload.height);
+
+        let scrolled_window = gtk::ScrolledWindow::new();
+        scrolled_window.set_child(Some(drawing_area));
+        overlay.set_child(Some(&scrolled_window));
+        overlay.add_overlay(model.toolbar.widget());
+
+        // the coordinates are relative to the drawing area, so they already match the canvas
+        let motion = gtk::EventControllerMotion::new();
+        let motion_sender = sender.clone();
+        motion.connect_motion(move |_, x, y| {
+            motion_sender.input(ScreenshotWindowInput::MouseEvent(MouseEvent::MouseMove(
+                Point { x, y },
+            )));
+        });
+        drawing_area.add_controller(motion);
+
+        let gesture = gtk::GestureClick::new();
+
+        let gesture_sender_pressed = sender.clone();
+        gesture.connect_pressed(move |_, i, x, y| {
+            gesture_sender_pressed.input(ScreenshotWindowInput::MouseEvent(
+                MouseEvent::MosePress {
+                    button: i,
+                    position: Point { x, y },
+                },
+            ));
+        });
+
+        let gesture_sender_released = sender.clone();
+        gesture.connect_released(move |_, i, x, y| {
+            gesture_sender_released.input(ScreenshotWindowInput::MouseEvent(
+                MouseEvent::MouseRelease {
+                    button: i,
+                    position: Point { x, y },
+                },
+            ));
+        });
+
+        gesture.set_propagation_phase(gtk::PropagationPhase::Bubble);
+        drawing_area.add_controller(gesture);
+
+        window.present();
+
+        ComponentParts { model, widgets: () }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            ScreenshotWindowInput::Draw(surface) => self.draw(Some(surface)),
+            ScreenshotWindowInput::Redraw => self.draw(None),
+            // the toolbar doesn't hide since there's only one window
+            ScreenshotWindowInput::EnterWindow | ScreenshotWindowInput::LeaveWindow => {}
+            ScreenshotWindowInput::MouseEvent(event) => sender
+                .output_sender()
+                .emit(ScreenshotWindowOutput::MouseEvent(event)),
+            ScreenshotWindowInput::ToolbarEvent(event) => sender
+                .output_sender()
+                .emit(ScreenshotWindowOutput::ToolbarEvent(event)),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use gtk::prelude::*;
+use notify_rust::Urgency;
+use relm4::prelude::*;
+use relm4::{RelmWidgetExt, Sender, SimpleComponent};
+
+use crate::{
+    cli::EditArgs,
+    config, export,
+    history::{self, Destination, Entry},
+};
+
+use super::{file_chooser::FileChooser, main_window::Command, notification::Notification};
+
+/// The height of the thumbnails in the list.
+const THUMBNAIL_HEIGHT: i32 = 96;
+
+/// Lists the screenshots of the history.
+pub struct HistoryWindowInit {
+    pub app_sender: Sender<Command>,
+    pub export_options: config::Export,
+}
+
+#[derive(Debug)]
+pub struct HistoryWindow {
+    entries: Vec<Entry>,
+    app_sender: Sender<Command>,
+    export_options: config::Export,
+}
+
+#[derive(Debug)]
+pub enum HistoryWindowInput {
+    Copy(usize),
+    Save(usize),
+    Delete(usize),
+    Edit(usize),
+}
+
+impl HistoryWindow {
+    pub fn open(init: HistoryWindowInit) {
+        let mut history_window = HistoryWindow::builder().launch(init);
+
+        history_window.widget().present();
+        history_window.detach_runtime();
+    }
+
+    fn notify(&self, msg: String, urgency: Urgency) {
+        self.app_sender
+            .send(Command::Notify(Notification { msg, urgency }))
+            .expect("Couldn't send notification command");
+    }
+
+    fn save(&self, entry: &Entry) {
+        let image_path = entry.image_path();
+        let app_sender = self.app_sender.clone();
+        let export_options = self.export_options.clone();
+
+        FileChooser::open(move |file| {
+            let Some(path) = file else {
+                return;
+            };
+
+            // the format is deduced from the extension, so we can't just copy the file
+            let result = image::open(&image_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|img| export::save(&img, &path, &export_options));
+
+            let notification = match result {
+                Ok(()) => Notification {
+                    msg: format!("Screenshot save to {}", path.to_string_lossy()),
+                    urgency: Urgency::Low,
+                },
+                Err(err) => Notification {
+                    msg: format!(
+                        "Couldn't save screenshot to {}: {:#}",
+                        path.to_string_lossy(),
+                        err
+                    ),
+                    urgency: Urgency::Critical,
+                },
+            };
+
+            app_sender
+                .send(Command::Notify(notification))
+                .expect("Couldn't send notification command");
+        });
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for HistoryWindow {
+    type Init = HistoryWindowInit;
+    type Input = HistoryWindowInput;
+    type Output = ();
+
+    view! {
+        root = gtk::Window {
+            set_title: Some("Flakeshot history"),
+            set_default_size: (640, 720),
+
+            gtk::ScrolledWindow {
+                set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                #[name(list)]
+                gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::None,
+                },
+            },
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = HistoryWindow {
+            entries: history::entries(),
+            app_sender: init.app_sender,
+            export_options: init.export_options,
+        };
+
+        let widgets = view_output!();
+        fill_list(&widgets.list, &model.entries, &sender);
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            HistoryWindowInput::Copy(index) => {
+                let path = self.entries[index].image_path();
+                self.app_sender
+                    .send(Command::CopyFileToClipboard(path))
+                    .expect("Couldn't send copy command");
+            }
+            HistoryWindowInput::Save(index) => self.save(&self.entries[index]),
+            HistoryWindowInput::Delete(index) => match self.entries[index].delete() {
+                Ok(()) => {
+                    self.entries.remove(index);
+                }
+                Err(err) => self.notify(format!("{:#}", err), Urgency::Critical),
+            },
+            HistoryWindowInput::Edit(index) => {
+                let path = self.entries[index].image_path();
+                self.app_sender
+                    .send(Command::Edit(EditArgs {
+                        path: Some(path),
+                        from_clipboard: false,
+                    }))
+                    .expect("Couldn't send edit command");
+            }
+        }
+    }
+
+    fn update_view(&self, widgets: &mut Self::Widgets, sender: ComponentSender<Self>) {
+        fill_list(&widgets.list, &self.entries, &sender);
+    }
+}
+
+fn fill_list(list: &gtk::ListBox, entries: &[Entry], sender: &ComponentSender<HistoryWindow>) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+
+    if entries.is_empty() {
+        list.append(&gtk::Label::new(Some("There are no screenshots yet.")));
+        return;
+    }
+
+    for (index, entry) in entries.iter().enumerate() {
+        list.append(&create_row(index, entry, sender));
+    }
+}
+
+fn create_row(index: usize, entry: &Entry, sender: &ComponentSender<HistoryWindow>) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    row.set_margin_all(6);
+
+    let thumbnail = gtk::Picture::for_filename(entry.thumbnail_path());
+    thumbnail.set_size_request(THUMBNAIL_HEIGHT * 16 / 9, THUMBNAIL_HEIGHT);
+    row.append(&thumbnail);
+
+    let destination = match &entry.metadata.destination {
+        Destination::Clipboard => "Copied to clipboard".to_string(),
+        Destination::File(path) => format!("Saved to {}", path.to_string_lossy()),
+        Destination::Upload(url) => format!("Uploaded to {}", url),
+    };
+    let description = gtk::Label::new(Some(&format!(
+        "{}\n{}\n{}",
+        entry.metadata.time.format("%Y-%m-%d %H:%M:%S"),
+        entry.metadata.outputs.join(", "),
+        destination
+    )));
+    description.set_hexpand(true);
+    description.set_xalign(0.);
+    row.append(&description);
+
+    let buttons = [
+        (
+            "edit-copy-symbolic",
+            "Copy to clipboard",
+            HistoryWindowInput::Copy as fn(usize) -> HistoryWindowInput,
+        ),
+        (
+            "document-save-symbolic",
+            "Save to file",
+            HistoryWindowInput::Save,
+        ),
+        (
+            "document-edit-symbolic",
+            "Open in editor",
+            HistoryWindowInput::Edit,
+        ),
+        ("user-trash-symbolic", "Delete", HistoryWindowInput::Delete),
+    ];
+
+    for (icon_name, tooltip, input) in buttons {
+        let button = gtk::Button::from_icon_name(icon_name);
+        button.set_tooltip_text(Some(tooltip));
+        button.set_valign(gtk::Align::Center);
+
+        let sender = sender.clone();
+        button.connect_clicked(move |_| sender.input(input(index)));
+        row.append(&button);
+    }
+
+    row
+}
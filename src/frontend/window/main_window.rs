@@ -1,24 +1,53 @@
-use std::{collections::HashMap, path::PathBuf, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    rc::Rc,
+    time::Duration,
+};
 
 use super::{
+    editor_window::{EditorWindowInit, EditorWindowModel},
+    history_window::{HistoryWindow, HistoryWindowInit},
     notification::Notification,
+    pin_window::{PinWindow, PinWindowInit},
     run_mode::RunMode,
     screenshot_window::{
         ScreenshotWindowInit, ScreenshotWindowInput, ScreenshotWindowModel, ScreenshotWindowOutput,
     },
 };
 use crate::{
-    backend::{self, MonitorInfo, OutputInfo},
+    autosave,
+    backend::{self, CaptureTarget, MonitorInfo, OutputInfo, Region},
+    cli::{EditArgs, GuiArgs},
+    clipboard,
     config::Config,
-    frontend::ui::ui_manager::UiManager,
-    tray,
+    frontend::{
+        shape::rectangle::Rectangle,
+        ui::{
+            drawable::SerializedDrawable,
+            toolbar::{DragImage, ToolbarEvent},
+            ui_manager::UiManager,
+        },
+    },
+    history::{self, Destination},
+    hooks,
+    project::{self, Project},
+    recorder::{RecordOptions, Recording},
+    timelapse::{Timelapse, TimelapseOptions},
+    tray::{
+        self,
+        hotkeys::{Hotkey, Modifiers},
+        Activity,
+    },
 };
+use anyhow::Context;
 use clap::crate_name;
 use gtk::prelude::*;
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
 use notify_rust::Urgency;
 use relm4::{gtk::Application, prelude::*};
-use tracing::error;
+use tracing::{error, info};
 
 const FLAKESHOT_SUMMARY: &str = "Flakeshot info";
 
@@ -38,12 +67,25 @@ pub struct Settings {
 pub struct AppModel {
     /// `None`: If the GUI hasn't been started
     /// `Some(UiManager)`: If the GUI has been started
-    ui_manager: Option<UiManager>,
+    ui_manager: Option<Rc<RefCell<UiManager>>>,
 
     /// Holds the controllers of all created windows when the GUI has started.
     /// It's empty, if the GUI has been closed.
     window_controllers: Vec<Controller<ScreenshotWindowModel>>,
 
+    /// `Some(Controller)`: If an existing image is opened in the editor window.
+    editor_controller: Option<Controller<EditorWindowModel>>,
+
+    /// `Some(Recording)`: If a region of the screen is currently recorded.
+    recording: Option<Recording>,
+
+    /// `Some(Timelapse)`: If a timelapse is currently running.
+    timelapse: Option<Timelapse>,
+
+    /// The open pin windows by their id.
+    pins: BTreeMap<usize, Controller<PinWindow>>,
+    next_pin_id: usize,
+
     settings: Settings,
 }
 
@@ -61,6 +103,68 @@ pub enum Command {
 
     /// Tells [`AppModel`] to create a notification.
     Notify(Notification),
+
+    /// Tells [`AppModel`] to close the GUI and to start recording the given region.
+    StartRecording(Region),
+
+    /// Tells [`AppModel`] to stop the current recording.
+    StopRecording,
+
+    /// Tells [`AppModel`] that the recording has been stored (or failed).
+    RecordingFinished,
+
+    /// Tells [`AppModel`] to start a timelapse or to stop the current one.
+    ToggleTimelapse,
+
+    /// Tells [`AppModel`] that the timelapse has stopped (or failed).
+    TimelapseFinished,
+
+    /// Tells [`AppModel`] to copy a screenshot of the target into the clipboard
+    /// without opening the GUI.
+    CaptureToClipboard(CaptureTarget),
+
+    /// Tells [`AppModel`] to open up the GUI after the given delay.
+    DelayedGui(Duration),
+
+    /// Tells [`AppModel`] to copy the image in the given file into the clipboard.
+    CopyFileToClipboard(PathBuf),
+
+    /// Tells [`AppModel`] to open the config file in the default editor of the user.
+    OpenConfig,
+
+    /// Tells [`AppModel`] to check the config file and apply the changes.
+    ReloadConfig,
+
+    /// Tells [`AppModel`] to open the window with the screenshot history.
+    OpenHistory,
+
+    /// Tells [`AppModel`] to remove the screenshots which violate the retention policy.
+    PruneHistory,
+
+    /// Tells [`AppModel`] to close the GUI while the screenshot is being uploaded.
+    Uploading,
+
+    /// Tells [`AppModel`] that the upload has finished (or failed).
+    UploadFinished(Notification),
+
+    /// Tells [`AppModel`] to close the GUI and to show the image in a new pin window.
+    Pin(RgbaImage),
+
+    /// Tells [`AppModel`] to close the pin window with the given id.
+    Unpin(usize),
+
+    /// Tells [`AppModel`] to copy the image into the clipboard.
+    CopyImageToClipboard(RgbaImage),
+
+    /// Tells [`AppModel`] to open the image of the file or the clipboard in the editor window.
+    Edit(EditArgs),
+
+    /// Tells [`AppModel`] to open the image which has been read from the clipboard in the
+    /// editor window. `Err` contains the reason why it couldn't be read.
+    EditClipboardImage(Result<RgbaImage, String>),
+
+    /// Tells [`AppModel`] to store a snapshot of the editor state.
+    Autosave,
 }
 
 impl AppModel {
@@ -68,6 +172,11 @@ impl AppModel {
         Self {
             ui_manager: None,
             window_controllers: vec![],
+            editor_controller: None,
+            recording: None,
+            timelapse: None,
+            pins: BTreeMap::new(),
+            next_pin_id: 0,
             settings,
         }
     }
@@ -84,27 +193,43 @@ impl AppModel {
         }
     }
 
+    fn load_config(&self) -> Config {
+        Config::load(&self.settings.config_path)
+            .map_err(|_| {
+                self.notify(Notification {
+                    msg: "Config is invalid! Please take a look into the logs.".to_string(),
+                    urgency: Urgency::Critical,
+                })
+            })
+            .unwrap_or_default()
+    }
+
     /// Start a new GUI session where a screenshot of all monitors
     /// are taken and opens up the screenshot-editor.
     fn start_gui(&mut self, sender: ComponentSender<Self>) {
+        if !matches!(self.settings.run_mode, RunMode::Record(_)) {
+            offer_recovery(&sender);
+        }
+
         let sender_ref = Rc::new(sender.clone());
         let mut monitors = get_monitors();
+        let config = self.load_config();
+        let key_bindings = Rc::new(get_key_bindings(&config));
 
-        let mut ui_manager = {
+        let ui_manager = {
             let (total_width, total_height) = get_total_view_size(&monitors.values().collect());
-            UiManager::new(
+            let options = match &self.settings.run_mode {
+                RunMode::Gui(args) => args.clone(),
+                _ => GuiArgs::default(),
+            };
+
+            Rc::new(RefCell::new(UiManager::new(
                 total_width,
                 total_height,
                 sender,
-                Config::load(&self.settings.config_path)
-                    .map_err(|_| {
-                        self.notify(Notification {
-                            msg: "Config is invalid! Please take a look into the logs.".to_string(),
-                            urgency: Urgency::Critical,
-                        })
-                    })
-                    .unwrap_or_default(),
-            )
+                config,
+                options,
+            )))
         };
 
         let screenshots =
@@ -113,23 +238,165 @@ impl AppModel {
         for screenshot in screenshots {
             self.init_monitor(
                 &app,
-                &mut ui_manager,
+                &ui_manager,
                 &sender_ref,
+                &key_bindings,
                 &screenshot,
                 &mut monitors,
             );
         }
 
-        ui_manager.persist_canvas();
+        ui_manager.borrow_mut().persist_canvas();
+
+        self.ui_manager = Some(ui_manager);
+    }
+
+    /// Opens the image of the file (or stdin) or of the clipboard in the editor window instead
+    /// of taking screenshots of the monitors. Projects are opened with their drawables.
+    fn start_editor(&mut self, args: EditArgs, sender: ComponentSender<Self>) {
+        let Some(path) = args.path else {
+            // the owner of the clipboard might take a while to answer
+            let config = self.load_config();
+            sender.spawn_oneshot_command(move || {
+                Command::EditClipboardImage(
+                    clipboard::paste_image(&config)
+                        .map(|image| image.to_rgba8())
+                        .map_err(|err| format!("{:#}", err)),
+                )
+            });
+            return;
+        };
+
+        let loaded = if project::is_project(&path) {
+            Project::load(&path).map(|project| {
+                let restore = (project.selection, project.drawables);
+                (DynamicImage::from(project.capture), Some(restore))
+            })
+        } else {
+            crate::open_image(&path).map(|image| (image, None))
+        };
+
+        // the restored session is covered by its own autosave from now on
+        if loaded.is_ok() && autosave::is_recovered(&path) {
+            autosave::remove_recovered(&path);
+        }
+
+        let title = if path.as_os_str() != "-" {
+            format!(
+                "{} - {}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                crate_name!()
+            )
+        } else {
+            crate_name!().to_string()
+        };
+
+        match loaded {
+            Ok((image, project)) => self.open_editor(image, project, title, sender),
+            Err(err) => self.fail_editor(format!("{:#}", err)),
+        }
+    }
+
+    /// Opens the editor with the image once it has been read from the clipboard.
+    fn edit_clipboard_image(
+        &mut self,
+        result: Result<RgbaImage, String>,
+        sender: ComponentSender<Self>,
+    ) {
+        match result {
+            Ok(image) => self.open_editor(
+                image.into(),
+                None,
+                format!("Clipboard - {}", crate_name!()),
+                sender,
+            ),
+            Err(err) => self.fail_editor(err),
+        }
+    }
+
+    fn fail_editor(&mut self, err: String) {
+        let msg = format!("Couldn't open the image in the editor: {}", err);
+        error!("{}", msg);
+        self.notify(Notification {
+            msg,
+            urgency: Urgency::Critical,
+        });
+
+        if self.settings.run_mode != RunMode::Tray {
+            self.quit();
+        }
+    }
+
+    fn open_editor(
+        &mut self,
+        image: DynamicImage,
+        project: Option<(Rectangle, Vec<SerializedDrawable>)>,
+        title: String,
+        sender: ComponentSender<Self>,
+    ) {
+        let config = self.load_config();
+        self.close_gui();
 
+        let sender_ref = Rc::new(sender.clone());
+        let key_bindings = Rc::new(get_key_bindings(&config));
+        let (width, height) = (image.width() as i32, image.height() as i32);
+
+        let ui_manager = Rc::new(RefCell::new(UiManager::new(
+            width,
+            height,
+            sender,
+            config,
+            GuiArgs::default(),
+        )));
+
+        let window = EditorWindowModel::builder();
+        register_keyboard_events(&window.root, sender_ref.clone(), key_bindings);
+        relm4::main_application().add_window(&window.root);
+
+        let window_controller = window
+            .launch(EditorWindowInit {
+                title,
+                width,
+                height,
+                parent_sender: sender_ref.clone(),
+                drag_image: get_drag_image(&ui_manager),
+            })
+            .forward(sender_ref.input_sender(), |event| {
+                AppInput::ScreenshotWindowOutput(event)
+            });
+
+        let sender_ui = window_controller.sender().clone();
+        {
+            let mut ui_manager = ui_manager.borrow_mut();
+            ui_manager.on_render(move |ui_manager| {
+                let surface = ui_manager
+                    .crop(0., 0., width, height)
+                    .expect("Couldn't crop surface for the editor.");
+                sender_ui
+                    .send(ScreenshotWindowInput::Draw(surface))
+                    .expect("Letting window redraw canvas failed.");
+            });
+
+            ui_manager
+                .stamp_image(0., 0., width as f64, height as f64, &image)
+                .expect("Couldn't stamp image.");
+            ui_manager.persist_canvas();
+
+            if let Some((selection, drawables)) = project {
+                ui_manager.restore_project(selection, drawables);
+            }
+        }
+
+        self.editor_controller = Some(window_controller);
         self.ui_manager = Some(ui_manager);
     }
 
     fn init_monitor(
         &mut self,
         app: &Application,
-        ui_manager: &mut UiManager,
+        ui_manager: &Rc<RefCell<UiManager>>,
         sender_ref: &Rc<ComponentSender<Self>>,
+        key_bindings: &Rc<Vec<(Hotkey, String)>>,
         (output_info, image): &(OutputInfo, DynamicImage),
         monitors: &mut HashMap<String, gtk4::gdk::Monitor>,
     ) {
@@ -143,6 +410,16 @@ impl AppModel {
                 .remove(&monitor_name.to_string())
                 .expect("We tried to access a non-existend monitor.");
 
+            ui_manager.borrow_mut().add_output(
+                monitor_name.clone(),
+                Region {
+                    x: monitor.geometry().x(),
+                    y: monitor.geometry().y(),
+                    width: monitor.geometry().width() as u32,
+                    height: monitor.geometry().height() as u32,
+                },
+            );
+
             let x = monitor.geometry().x();
             let y = monitor.geometry().y();
             let width = monitor.geometry().width();
@@ -153,7 +430,7 @@ impl AppModel {
 
         let window = {
             let window = ScreenshotWindowModel::builder();
-            register_keyboard_events(&window.root, sender_ref.clone());
+            register_keyboard_events(&window.root, sender_ref.clone(), key_bindings.clone());
             app.add_window(&window.root);
             window.root.set_visible(false);
 
@@ -166,6 +443,7 @@ impl AppModel {
                 .launch(ScreenshotWindowInit {
                     monitor,
                     parent_sender: sender_ref.clone(),
+                    drag_image: get_drag_image(ui_manager),
                 })
                 .forward(sender_ref.input_sender(), |event| {
                     AppInput::ScreenshotWindowOutput(event)
@@ -173,7 +451,7 @@ impl AppModel {
 
             // subscribe to canvas changes
             let sender_ui = window_controller.sender().clone();
-            ui_manager.on_render(move |ui_manager| {
+            ui_manager.borrow_mut().on_render(move |ui_manager| {
                 let surface = ui_manager
                     .crop(x as f64, y as f64, width, height)
                     .expect("Couldn't crop surface for monitor.");
@@ -189,6 +467,7 @@ impl AppModel {
 
         // add screenshot of monitor to image
         ui_manager
+            .borrow_mut()
             .stamp_image(x as f64, y as f64, width as f64, height as f64, image)
             .expect("Couldn't stamp image.");
     }
@@ -196,17 +475,284 @@ impl AppModel {
     /// Closes the GUI if [`AppModel`] is running as a tray.
     fn close(&mut self) {
         match self.settings.run_mode {
-            RunMode::Tray => {
-                self.ui_manager = None;
-                for controller in &self.window_controllers {
-                    controller.widget().close();
+            // the pins keep us alive
+            RunMode::Gui(_) | RunMode::Edit(_) if !self.pins.is_empty() => self.close_gui(),
+            RunMode::Tray => self.close_gui(),
+            RunMode::Gui(_) | RunMode::Edit(_) | RunMode::Record(_) | RunMode::Timelapse(_) => {
+                self.quit()
+            }
+        };
+    }
+
+    /// Closes the windows and UI without quitting.
+    fn close_gui(&mut self) {
+        self.ui_manager = None;
+        autosave::discard();
+        for controller in &self.window_controllers {
+            controller.widget().close();
+        }
+
+        // closing the editor window would send another `Command::Close`
+        if let Some(controller) = self.editor_controller.take() {
+            controller.widget().destroy();
+        }
+    }
+
+    fn start_recording(&mut self, region: Region, sender: ComponentSender<Self>) {
+        if self.recording.is_some() {
+            self.notify(Notification {
+                msg: "There's already a recording running.".to_string(),
+                urgency: Urgency::Normal,
+            });
+            return;
+        }
+
+        let args = match &self.settings.run_mode {
+            RunMode::Record(args) => Some(args),
+            _ => None,
+        };
+
+        match RecordOptions::new(&self.load_config().recording, args) {
+            Ok(options) => {
+                self.close_gui();
+                tray::set_activity(Activity::Recording, true);
+                self.recording = Some(Recording::start(
+                    region,
+                    options,
+                    sender.command_sender().clone(),
+                ));
+            }
+            Err(err) => self.notify(Notification {
+                msg: format!("Couldn't start the recording: {:#}", err),
+                urgency: Urgency::Critical,
+            }),
+        }
+    }
+
+    fn finish_recording(&mut self) {
+        self.recording = None;
+        tray::set_activity(Activity::Recording, false);
+
+        if self.settings.run_mode != RunMode::Tray {
+            self.quit();
+        }
+    }
+
+    fn toggle_timelapse(&mut self, sender: ComponentSender<Self>) {
+        if let Some(timelapse) = &self.timelapse {
+            timelapse.stop();
+            return;
+        }
+
+        let args = match &self.settings.run_mode {
+            RunMode::Timelapse(args) => Some(args),
+            _ => None,
+        };
+
+        match TimelapseOptions::new(&self.load_config().timelapse, args) {
+            Ok(options) => {
+                tray::set_activity(Activity::Timelapse, true);
+                self.timelapse = Some(Timelapse::start(options, sender.command_sender().clone()))
+            }
+            Err(err) => {
+                self.notify(Notification {
+                    msg: format!("Couldn't start the timelapse: {:#}", err),
+                    urgency: Urgency::Critical,
+                });
+
+                if self.settings.run_mode != RunMode::Tray {
+                    self.quit();
                 }
             }
-            RunMode::Gui => self.quit(),
+        }
+    }
+
+    fn start_upload(&mut self) {
+        self.close_gui();
+        tray::set_activity(Activity::Uploading, true);
+    }
+
+    fn finish_upload(&mut self, notification: Notification) {
+        tray::set_activity(Activity::Uploading, false);
+        self.notify(notification);
+
+        if self.settings.run_mode != RunMode::Tray {
+            self.quit();
+        }
+    }
+
+    fn finish_timelapse(&mut self) {
+        self.timelapse = None;
+        tray::set_activity(Activity::Timelapse, false);
+
+        if self.settings.run_mode != RunMode::Tray {
+            self.quit();
+        }
+    }
+
+    fn capture_to_clipboard(&self, target: CaptureTarget, sender: ComponentSender<Self>) {
+        let config = self.load_config();
+        let command_sender = sender.command_sender().clone();
+
+        sender.spawn_oneshot_command(move || {
+            tray::set_activity(Activity::Capturing, true);
+            let result = backend::capture(&target)
+                .map_err(anyhow::Error::from)
+                .and_then(|image| {
+                    let image = DynamicImage::from(image);
+                    clipboard::copy_image(&config, &image)?;
+                    Ok(image)
+                });
+            tray::set_activity(Activity::Capturing, false);
+
+            Command::Notify(match result {
+                Ok(image) => {
+                    let event = hooks::Event::new(
+                        &Destination::Clipboard,
+                        image.width(),
+                        image.height(),
+                        vec![],
+                    );
+                    hooks::run(&config.hooks, &event, move |msg| {
+                        command_sender.emit(Command::Notify(Notification {
+                            msg,
+                            urgency: Urgency::Critical,
+                        }))
+                    });
+
+                    Notification {
+                        msg: "Screenshot saved to clipboard.".to_string(),
+                        urgency: Urgency::Low,
+                    }
+                }
+                Err(err) => Notification {
+                    msg: format!("Couldn't copy the screenshot: {:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            })
+        });
+    }
+
+    fn pin(&mut self, image: RgbaImage, sender: ComponentSender<Self>) {
+        self.close_gui();
+
+        let id = self.next_pin_id;
+        self.next_pin_id += 1;
+
+        let pin = PinWindow::builder()
+            .launch(PinWindowInit {
+                id,
+                image,
+                app_sender: sender.command_sender().clone(),
+                export_options: self.load_config().export,
+            })
+            .detach();
+        relm4::main_application().add_window(pin.widget());
+        pin.widget().present();
+
+        self.pins.insert(id, pin);
+    }
+
+    fn unpin(&mut self, id: usize) {
+        if let Some(pin) = self.pins.remove(&id) {
+            pin.widget().close();
+        }
+
+        if self.pins.is_empty()
+            && self.ui_manager.is_none()
+            && self.settings.run_mode != RunMode::Tray
+        {
+            self.quit();
+        }
+    }
+
+    fn copy_file_to_clipboard(&self, path: PathBuf, sender: ComponentSender<Self>) {
+        let config = self.load_config();
+
+        sender.spawn_oneshot_command(move || {
+            let result = image::open(&path)
+                .with_context(|| format!("Couldn't open {}", path.to_string_lossy()))
+                .and_then(|image| clipboard::copy_image(&config, &image));
+
+            Command::Notify(match result {
+                Ok(()) => Notification {
+                    msg: "Screenshot saved to clipboard.".to_string(),
+                    urgency: Urgency::Low,
+                },
+                Err(err) => Notification {
+                    msg: format!("Couldn't copy the screenshot: {:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            })
+        });
+    }
+
+    fn copy_image_to_clipboard(&self, image: RgbaImage, sender: ComponentSender<Self>) {
+        let config = self.load_config();
+
+        sender.spawn_oneshot_command(move || {
+            Command::Notify(match clipboard::copy_image(&config, &image.into()) {
+                Ok(()) => Notification {
+                    msg: "Screenshot saved to clipboard.".to_string(),
+                    urgency: Urgency::Low,
+                },
+                Err(err) => Notification {
+                    msg: format!("Couldn't copy the screenshot: {:#}", err),
+                    urgency: Urgency::Critical,
+                },
+            })
+        });
+    }
+
+    fn open_config(&self) {
+        let path = &self.settings.config_path;
+
+        let result = if path.exists() {
+            Ok(())
+        } else {
+            std::fs::write(path, toml::to_string_pretty(&Config::default()).unwrap())
+                .context("Couldn't create the config file")
         };
+
+        if let Err(err) = result.and_then(|()| crate::open_with_default_app(path)) {
+            self.notify(Notification {
+                msg: format!("Couldn't open the config: {:#}", err),
+                urgency: Urgency::Critical,
+            });
+        }
+    }
+
+    /// The config is loaded whenever it's needed, so we only have to validate it
+    /// and to refresh the tray.
+    fn reload_config(&self) {
+        if Config::load(&self.settings.config_path).is_ok() {
+            tray::refresh();
+            self.notify(Notification {
+                msg: "Config reloaded. Changed hotkeys are applied after a restart.".to_string(),
+                urgency: Urgency::Low,
+            });
+        } else {
+            self.notify(Notification {
+                msg: "Config is invalid! Please take a look into the logs.".to_string(),
+                urgency: Urgency::Critical,
+            });
+        }
+    }
+
+    fn prune_history(&self, sender: ComponentSender<Self>) {
+        let config = self.load_config().history;
+
+        sender.spawn_command(move |_| match history::prune(&config) {
+            Ok(report) if report.removed > 0 => {
+                info!("Removed {} screenshot(s) from the history.", report.removed)
+            }
+            Ok(_) => {}
+            Err(err) => error!("Couldn't prune the history: {:#}", err),
+        });
     }
 
     fn quit(&mut self) {
+        autosave::discard();
         relm4::main_application().quit();
     }
 }
@@ -236,17 +782,40 @@ impl Component for AppModel {
     ) -> relm4::ComponentParts<Self> {
         let mut model = Self::new(payload);
 
-        match model.settings.run_mode {
-            RunMode::Gui => model.start_gui(sender),
-            RunMode::Tray => sender
-                .command(|out, shutdown| shutdown.register(tray::start(out)).drop_on_shutdown()),
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(async move {
+                    loop {
+                        tokio::time::sleep(autosave::INTERVAL).await;
+                        if out.send(Command::Autosave).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .drop_on_shutdown()
+        });
+
+        match model.settings.run_mode.clone() {
+            RunMode::Gui(_) | RunMode::Record(_) => model.start_gui(sender),
+            RunMode::Edit(args) => model.start_editor(args, sender),
+            RunMode::Timelapse(_) => model.toggle_timelapse(sender),
+            RunMode::Tray => {
+                let config_path = model.settings.config_path.clone();
+
+                sender.command(|out, shutdown| {
+                    shutdown
+                        .register(tray::start(out, config_path))
+                        .drop_on_shutdown()
+                })
+            }
         }
 
         ComponentParts { model, widgets: () }
     }
 
     fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>, _root: &Self::Root) {
-        if let Some(ui_manager) = &mut self.ui_manager {
+        if let Some(ui_manager) = &self.ui_manager {
+            let mut ui_manager = ui_manager.borrow_mut();
             match message {
                 AppInput::ScreenshotWindowOutput(ScreenshotWindowOutput::ToolbarEvent(event)) => {
                     ui_manager.handle_tool_event(event)
@@ -268,6 +837,40 @@ impl Component for AppModel {
             Command::Quit => self.quit(),
             Command::Gui => self.start_gui(sender),
             Command::Close => self.close(),
+            Command::StartRecording(region) => self.start_recording(region, sender),
+            Command::StopRecording => {
+                if let Some(recording) = &self.recording {
+                    recording.stop();
+                }
+            }
+            Command::RecordingFinished => self.finish_recording(),
+            Command::ToggleTimelapse => self.toggle_timelapse(sender),
+            Command::TimelapseFinished => self.finish_timelapse(),
+            Command::CaptureToClipboard(target) => self.capture_to_clipboard(target, sender),
+            Command::DelayedGui(delay) => sender.oneshot_command(async move {
+                tokio::time::sleep(delay).await;
+                Command::Gui
+            }),
+            Command::CopyFileToClipboard(path) => self.copy_file_to_clipboard(path, sender),
+            Command::OpenConfig => self.open_config(),
+            Command::ReloadConfig => self.reload_config(),
+            Command::OpenHistory => HistoryWindow::open(HistoryWindowInit {
+                app_sender: sender.command_sender().clone(),
+                export_options: self.load_config().export,
+            }),
+            Command::PruneHistory => self.prune_history(sender),
+            Command::Uploading => self.start_upload(),
+            Command::UploadFinished(notification) => self.finish_upload(notification),
+            Command::Pin(image) => self.pin(image, sender),
+            Command::Unpin(id) => self.unpin(id),
+            Command::CopyImageToClipboard(image) => self.copy_image_to_clipboard(image, sender),
+            Command::Edit(args) => self.start_editor(args, sender),
+            Command::EditClipboardImage(result) => self.edit_clipboard_image(result, sender),
+            Command::Autosave => {
+                if let Some(ui_manager) = &self.ui_manager {
+                    ui_manager.borrow_mut().autosave();
+                }
+            }
             Command::Notify(noti) => {
                 if noti.urgency == Urgency::Critical {
                     error!(noti.msg);
@@ -278,6 +881,53 @@ impl Component for AppModel {
     }
 }
 
+/// Offers to restore the editor session of the last run if it didn't end normally.
+fn offer_recovery(sender: &ComponentSender<AppModel>) {
+    let Some(path) = autosave::take_recovered() else {
+        return;
+    };
+
+    sender.spawn_command(move |out| {
+        let handle = match notify_rust::Notification::new()
+            .appname(crate_name!())
+            .summary(FLAKESHOT_SUMMARY)
+            .body("The last editor session didn't end normally. Do you want to restore it?")
+            .action("restore", "Restore")
+            .timeout(notify_rust::Timeout::Never)
+            .show()
+        {
+            Ok(handle) => handle,
+            Err(err) => {
+                error!("Couldn't show notification: {}", err);
+                return;
+            }
+        };
+
+        // the editor removes the snapshot once it's restored
+        handle.wait_for_action(|action| {
+            if action == "restore" {
+                let _ = out.send(Command::Edit(EditArgs {
+                    path: Some(path),
+                    from_clipboard: false,
+                }));
+            } else {
+                autosave::remove_recovered(&path);
+            }
+        });
+    });
+}
+
+/// Lets the toolbars render the screenshot only once it's actually dragged out of them.
+fn get_drag_image(ui_manager: &Rc<RefCell<UiManager>>) -> DragImage {
+    let ui_manager = Rc::downgrade(ui_manager);
+
+    Rc::new(move || {
+        let ui_manager = ui_manager.upgrade()?;
+        let ui_manager = ui_manager.try_borrow().ok()?;
+        ui_manager.get_drag_image()
+    })
+}
+
 fn get_monitors() -> HashMap<String, gtk4::gdk::Monitor> {
     let monitor_list_model = gtk4::gdk::Display::default()
         .expect("GDK did not provide a display for us.")
@@ -322,15 +972,75 @@ fn get_total_view_size(monitors: &Vec<&gtk4::gdk::Monitor>) -> (i32, i32) {
     (width, height)
 }
 
-fn register_keyboard_events(window: &gtk::Window, sender: Rc<ComponentSender<AppModel>>) {
+/// Parses the keys of the pipelines. Invalid keys are logged and skipped.
+fn get_key_bindings(config: &Config) -> Vec<(Hotkey, String)> {
+    config
+        .pipelines
+        .keys()
+        .filter_map(|key| match key.parse() {
+            Ok(hotkey) => Some((hotkey, key.clone())),
+            Err(err) => {
+                error!("Ignoring pipeline: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns `true` if the pressed key with the given modifiers is the hotkey.
+fn is_pressed(hotkey: &Hotkey, key: gtk4::gdk::Key, state: gtk4::gdk::ModifierType) -> bool {
+    use gtk4::gdk::ModifierType;
+
+    let modifiers = Modifiers {
+        shift: state.contains(ModifierType::SHIFT_MASK),
+        ctrl: state.contains(ModifierType::CONTROL_MASK),
+        alt: state.contains(ModifierType::ALT_MASK),
+        logo: state.contains(ModifierType::SUPER_MASK),
+    };
+
+    let expected = match hotkey.key.to_lowercase().as_str() {
+        "enter" => "return".to_string(),
+        name => name.to_string(),
+    };
+    let pressed = key.to_lower().name().map(|name| name.to_lowercase());
+
+    hotkey.modifiers == modifiers && pressed.as_deref() == Some(expected.as_str())
+}
+
+fn register_keyboard_events(
+    window: &gtk::Window,
+    sender: Rc<ComponentSender<AppModel>>,
+    key_bindings: Rc<Vec<(Hotkey, String)>>,
+) {
     let event_controller = gtk::EventControllerKey::new();
 
-    event_controller.connect_key_pressed(move |_, key, _, _| {
+    event_controller.connect_key_pressed(move |_, key, _, state| {
         if let gtk4::gdk::Key::Escape = key {
             sender
                 .command_sender()
                 .send(Command::Close)
                 .expect("Couldn't send quit command");
+
+            return gtk::glib::Propagation::Proceed;
+        }
+
+        if let Some((_, pipeline)) = key_bindings
+            .iter()
+            .find(|(hotkey, _)| is_pressed(hotkey, key, state))
+        {
+            sender.input(AppInput::ScreenshotWindowOutput(
+                ScreenshotWindowOutput::ToolbarEvent(ToolbarEvent::RunPipeline(pipeline.clone())),
+            ));
+
+            return gtk::glib::Propagation::Stop;
+        }
+
+        if let gtk4::gdk::Key::Return | gtk4::gdk::Key::KP_Enter = key {
+            sender.input(AppInput::ScreenshotWindowOutput(
+                ScreenshotWindowOutput::ToolbarEvent(ToolbarEvent::Confirm),
+            ));
+
+            return gtk::glib::Propagation::Stop;
         }
 
         gtk::glib::Propagation::Proceed
@@ -1,5 +1,8 @@
+pub mod editor_window;
 pub mod file_chooser;
+pub mod history_window;
 pub mod main_window;
 pub mod notification;
+pub mod pin_window;
 pub mod run_mode;
 pub mod screenshot_window;
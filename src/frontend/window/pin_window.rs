@@ -0,0 +1,342 @@
+use gdk4_x11::X11Surface;
+use gtk::{
+    gdk, glib,
+    prelude::{ButtonExt, EventControllerExt, GestureDragExt, GestureSingleExt, PopoverExt},
+};
+use gtk4_layer_shell::LayerShell;
+use image::RgbaImage;
+use notify_rust::Urgency;
+use relm4::{
+    gtk::{
+        self,
+        glib::object::Cast,
+        prelude::{BoxExt, GtkWindowExt, NativeExt, WidgetExt},
+    },
+    ComponentParts, ComponentSender, Sender, SimpleComponent,
+};
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{AtomEnum, ConnectionExt, PropMode},
+    wrapper::ConnectionExt as _,
+};
+
+use crate::{backend::is_wayland, config, export};
+
+use super::{file_chooser::FileChooser, main_window::Command, notification::Notification};
+
+/// The maximum width and height of a new pin. Bigger images are scaled down.
+const MAX_INITIAL_SIZE: f64 = 640.;
+
+/// The factor which is applied to the scale with each scroll step.
+const SCALE_STEP: f64 = 1.1;
+
+/// The opacity which is added or removed with each scroll step while Ctrl is pressed.
+const OPACITY_STEP: f64 = 0.1;
+
+/// The distance of a new pin to the top left corner of the output (wayland only).
+const INITIAL_MARGIN: i32 = 64;
+
+/// Shows a screenshot in a small window which stays above all other windows.
+pub struct PinWindowInit {
+    pub id: usize,
+    pub image: RgbaImage,
+    pub app_sender: Sender<Command>,
+    pub export_options: config::Export,
+}
+
+#[derive(Debug)]
+pub struct PinWindow {
+    id: usize,
+    image: RgbaImage,
+    app_sender: Sender<Command>,
+    export_options: config::Export,
+
+    window: gtk::Window,
+    picture: gtk::Picture,
+    menu: gtk::Popover,
+
+    scale: f64,
+
+    /// The position of the layer surface (wayland only).
+    margins: (i32, i32),
+}
+
+#[derive(Debug, Clone)]
+pub enum PinWindowInput {
+    /// Scroll up (`true`) or down (`false`).
+    Scale(bool),
+    Opacity(bool),
+
+    /// Move the layer surface by the given offset (wayland only).
+    Move(f64, f64),
+
+    ShowMenu(f64, f64),
+    Copy,
+    Save,
+    Close,
+}
+
+impl PinWindow {
+    fn resize(&self) {
+        let width = (self.image.width() as f64 * self.scale).round() as i32;
+        let height = (self.image.height() as f64 * self.scale).round() as i32;
+
+        self.picture.set_size_request(width.max(1), height.max(1));
+        self.window.set_default_size(width.max(1), height.max(1));
+    }
+
+    fn save(&self) {
+        let image = self.image.clone();
+        let app_sender = self.app_sender.clone();
+        let export_options = self.export_options.clone();
+
+        FileChooser::open(move |file| {
+            let Some(path) = file else {
+                return;
+            };
+
+            let notification = match export::save(&image.into(), &path, &export_options) {
+                Ok(()) => Notification {
+                    msg: format!("Screenshot save to {}", path.to_string_lossy()),
+                    urgency: Urgency::Low,
+                },
+                Err(err) => Notification {
+                    msg: format!(
+                        "Couldn't save screenshot to {}: {:#}",
+                        path.to_string_lossy(),
+                        err
+                    ),
+                    urgency: Urgency::Critical,
+                },
+            };
+
+            app_sender
+                .send(Command::Notify(notification))
+                .expect("Couldn't send notification command");
+        });
+    }
+}
+
+impl SimpleComponent for PinWindow {
+    type Input = PinWindowInput;
+    type Output = ();
+    type Init = PinWindowInit;
+    type Root = gtk::Window;
+    type Widgets = ();
+
+    fn init_root() -> Self::Root {
+        let window = gtk::Window::new();
+
+        if is_wayland() {
+            window.init_layer_shell();
+            window.set_layer(gtk4_layer_shell::Layer::Top);
+            window.set_anchor(gtk4_layer_shell::Edge::Top, true);
+            window.set_anchor(gtk4_layer_shell::Edge::Left, true);
+            window.set_margin(gtk4_layer_shell::Edge::Top, INITIAL_MARGIN);
+            window.set_margin(gtk4_layer_shell::Edge::Left, INITIAL_MARGIN);
+            window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::OnDemand);
+        } else {
+            window.connect_realize(|window| {
+                if let Err(err) = keep_above(window) {
+                    tracing::warn!("Couldn't keep the pin above other windows: {}", err);
+                }
+            });
+        }
+
+        window.set_decorated(false);
+        window.set_resizable(false);
+        window.set_title(Some("Flakeshot pin"));
+
+        window
+    }
+
+    fn init(
+        init: Self::Init,
+        window: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let (width, height) = init.image.dimensions();
+        let texture = gdk::MemoryTexture::new(
+            width as i32,
+            height as i32,
+            gdk::MemoryFormat::R8g8b8a8,
+            &glib::Bytes::from_owned(init.image.as_raw().clone()),
+            width as usize * 4,
+        );
+
+        let picture = gtk::Picture::for_paintable(&texture);
+        picture.set_can_shrink(true);
+
+        // dragging the handle moves the window (X11), layer surfaces are moved by their margins
+        let handle = gtk::WindowHandle::new();
+        handle.set_child(Some(&picture));
+        window.set_child(Some(&handle));
+
+        let menu = create_menu(&sender);
+        menu.set_parent(&picture);
+
+        // Scroll: scale, Ctrl+Scroll: opacity
+        let scroll = gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        let scroll_sender = sender.clone();
+        scroll.connect_scroll(move |controller, _, dy| {
+            let up = dy < 0.;
+            if controller
+                .current_event_state()
+                .contains(gdk::ModifierType::CONTROL_MASK)
+            {
+                scroll_sender.input(PinWindowInput::Opacity(up));
+            } else {
+                scroll_sender.input(PinWindowInput::Scale(up));
+            }
+
+            glib::Propagation::Stop
+        });
+        picture.add_controller(scroll);
+
+        // right click: context menu
+        let click = gtk::GestureClick::new();
+        click.set_button(gdk::BUTTON_SECONDARY);
+        let click_sender = sender.clone();
+        click.connect_pressed(move |_, _, x, y| {
+            click_sender.input(PinWindowInput::ShowMenu(x, y));
+        });
+        picture.add_controller(click);
+
+        if is_wayland() {
+            let drag = gtk::GestureDrag::new();
+            drag.set_button(gdk::BUTTON_PRIMARY);
+            let drag_sender = sender.clone();
+            drag.connect_drag_update(move |_, x, y| {
+                drag_sender.input(PinWindowInput::Move(x, y));
+            });
+            picture.add_controller(drag);
+        }
+
+        let key = gtk::EventControllerKey::new();
+        let key_sender = sender.clone();
+        key.connect_key_pressed(move |_, key, _, _| {
+            if let gdk::Key::Escape = key {
+                key_sender.input(PinWindowInput::Close);
+            }
+
+            glib::Propagation::Proceed
+        });
+        window.add_controller(key);
+
+        let close_sender = init.app_sender.clone();
+        let id = init.id;
+        window.connect_close_request(move |_| {
+            let _ = close_sender.send(Command::Unpin(id));
+            glib::Propagation::Proceed
+        });
+
+        let model = PinWindow {
+            id: init.id,
+            scale: (MAX_INITIAL_SIZE / width.max(height) as f64).min(1.),
+            image: init.image,
+            app_sender: init.app_sender,
+            export_options: init.export_options,
+            window: window.clone(),
+            picture,
+            menu,
+            margins: (INITIAL_MARGIN, INITIAL_MARGIN),
+        };
+        model.resize();
+
+        ComponentParts { model, widgets: () }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            PinWindowInput::Scale(up) => {
+                let factor = if up { SCALE_STEP } else { 1. / SCALE_STEP };
+                self.scale = (self.scale * factor).clamp(0.1, 8.);
+                self.resize();
+            }
+            PinWindowInput::Opacity(up) => {
+                let step = if up { OPACITY_STEP } else { -OPACITY_STEP };
+                self.window
+                    .set_opacity((self.window.opacity() + step).clamp(0.2, 1.));
+            }
+            PinWindowInput::Move(x, y) => {
+                // the offset is relative to the surface which moves along with the pointer
+                self.margins.0 += x.round() as i32;
+                self.margins.1 += y.round() as i32;
+                self.window
+                    .set_margin(gtk4_layer_shell::Edge::Left, self.margins.0);
+                self.window
+                    .set_margin(gtk4_layer_shell::Edge::Top, self.margins.1);
+            }
+            PinWindowInput::ShowMenu(x, y) => {
+                self.menu
+                    .set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                self.menu.popup();
+            }
+            PinWindowInput::Copy => {
+                self.menu.popdown();
+                self.app_sender
+                    .send(Command::CopyImageToClipboard(self.image.clone()))
+                    .expect("Couldn't send copy command");
+            }
+            PinWindowInput::Save => {
+                self.menu.popdown();
+                self.save();
+            }
+            PinWindowInput::Close => {
+                self.menu.popdown();
+                self.app_sender
+                    .send(Command::Unpin(self.id))
+                    .expect("Couldn't send unpin command");
+            }
+        }
+    }
+}
+
+fn create_menu(sender: &ComponentSender<PinWindow>) -> gtk::Popover {
+    let menu = gtk::Popover::new();
+    menu.set_has_arrow(false);
+
+    let items = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    for (label, input) in [
+        ("Copy", PinWindowInput::Copy),
+        ("Save", PinWindowInput::Save),
+        ("Close", PinWindowInput::Close),
+    ] {
+        let button = gtk::Button::with_label(label);
+        button.add_css_class("flat");
+
+        let sender = sender.clone();
+        button.connect_clicked(move |_| sender.input(input.clone()));
+        items.append(&button);
+    }
+    menu.set_child(Some(&items));
+
+    menu
+}
+
+/// Asks the window manager to keep the window above all other windows (`_NET_WM_STATE_ABOVE`).
+///
+/// The property has to be set before the window is mapped, so it has to be called on realize.
+fn keep_above(window: &gtk::Window) -> Result<(), Box<dyn std::error::Error>> {
+    let Ok(surface) = window.surface().downcast::<X11Surface>() else {
+        return Ok(());
+    };
+
+    let (conn, _) = x11rb::connect(None)?;
+    let wm_state = conn.intern_atom(false, b"_NET_WM_STATE")?.reply()?.atom;
+    let above = conn
+        .intern_atom(false, b"_NET_WM_STATE_ABOVE")?
+        .reply()?
+        .atom;
+
+    conn.change_property32(
+        PropMode::REPLACE,
+        surface.xid() as u32,
+        wm_state,
+        AtomEnum::ATOM,
+        &[above],
+    )?;
+    conn.flush()?;
+
+    Ok(())
+}
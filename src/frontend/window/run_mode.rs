@@ -1,21 +1,27 @@
-use crate::cli::Command;
+use crate::cli::{Command, EditArgs, GuiArgs, RecordArgs, TimelapseArgs};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RunMode {
     Tray,
-    Gui,
+    Gui(GuiArgs),
+    Edit(EditArgs),
+    Record(RecordArgs),
+    Timelapse(TimelapseArgs),
 }
 
 impl From<Command> for RunMode {
     fn from(value: Command) -> Self {
         match value {
-            Command::Gui => Self::Gui,
+            Command::Gui(args) => Self::Gui(args),
+            Command::Edit(args) => Self::Edit(args),
             Command::Tray => Self::Tray,
-            Command::PrintDefaultConfig => {
-                panic!(
-                    "There's no run mode for {:?} defined.",
-                    Command::PrintDefaultConfig
-                )
+            Command::Record(args) => Self::Record(args),
+            Command::Timelapse(args) => Self::Timelapse(args),
+            Command::PrintDefaultConfig
+            | Command::Capture(_)
+            | Command::History(_)
+            | Command::ServeClipboard => {
+                panic!("There's no run mode for {:?} defined.", value)
             }
         }
     }
@@ -25,7 +25,7 @@ use crate::{
     backend::is_wayland,
     frontend::{
         shape::point::Point,
-        ui::toolbar::{Toolbar, ToolbarEvent},
+        ui::toolbar::{DragImage, Toolbar, ToolbarEvent, ToolbarInit},
     },
 };
 
@@ -34,6 +34,7 @@ use super::main_window::AppModel;
 pub struct ScreenshotWindowInit {
     pub monitor: gtk4::gdk::Monitor,
     pub parent_sender: Rc<relm4::ComponentSender<AppModel>>,
+    pub drag_image: DragImage,
 }
 
 #[derive(Debug)]
@@ -70,7 +71,10 @@ pub enum ScreenshotWindowOutput {
 impl ScreenshotWindowModel {
     fn init(payload: ScreenshotWindowInit, input_sender: &Sender<ScreenshotWindowInput>) -> Self {
         let toolbar = Toolbar::builder()
-            .launch(())
+            .launch(ToolbarInit {
+                drag_image: payload.drag_image,
+                recordable: true,
+            })
             .forward(input_sender, |event| {
                 ScreenshotWindowInput::ToolbarEvent(event)
             });
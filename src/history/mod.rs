@@ -0,0 +1,313 @@
+//! Keeps a copy of every confirmed screenshot in the XDG data directory.
+//!
+//! Each entry consists of three files in [`HISTORY_DIR`] which share the id of the entry:
+//! the screenshot (`<id>.png`), a thumbnail (`<id>.thumb.png`) and its [`Metadata`]
+//! (`<id>.json`).
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread::JoinHandle,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Duration, Local};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{backend::Region, config, get_xdg};
+
+/// The subdirectory of the XDG data directory where the history is stored.
+const HISTORY_DIR: &str = "history";
+
+/// The maximum width and height of a thumbnail.
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Skip the history for the next screenshot.
+static INCOGNITO: AtomicBool = AtomicBool::new(false);
+
+/// The threads of [`add_in_background`] which are still writing.
+static WRITING: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Where the screenshot has been sent to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Destination {
+    Clipboard,
+    File(PathBuf),
+    Upload(String),
+}
+
+/// Describes a screenshot of the history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub time: DateTime<Local>,
+
+    /// The names of the outputs which are (partially) visible in the screenshot.
+    pub outputs: Vec<String>,
+
+    /// The selected region in global coordinates.
+    pub region: Option<Region>,
+
+    pub destination: Destination,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub id: String,
+    pub metadata: Metadata,
+}
+
+impl Entry {
+    pub fn image_path(&self) -> PathBuf {
+        get_history_dir_path().join(format!("{}.png", self.id))
+    }
+
+    pub fn thumbnail_path(&self) -> PathBuf {
+        get_history_dir_path().join(format!("{}.thumb.png", self.id))
+    }
+
+    pub fn metadata_path(&self) -> PathBuf {
+        get_history_dir_path().join(format!("{}.json", self.id))
+    }
+
+    /// Returns the size of all files of the entry in bytes.
+    pub fn size(&self) -> u64 {
+        [
+            self.image_path(),
+            self.thumbnail_path(),
+            self.metadata_path(),
+        ]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+    }
+
+    /// Removes all files of the entry.
+    pub fn delete(&self) -> anyhow::Result<()> {
+        for path in [
+            self.image_path(),
+            self.thumbnail_path(),
+            self.metadata_path(),
+        ] {
+            match std::fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err)
+                        .with_context(|| format!("Couldn't remove {}", path.to_string_lossy()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of [`prune`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    pub removed: usize,
+
+    /// The amount of freed bytes.
+    pub freed: u64,
+}
+
+/// Skips the history for the next screenshot if `true`.
+pub fn set_incognito(incognito: bool) {
+    INCOGNITO.store(incognito, Ordering::Relaxed);
+    crate::tray::refresh();
+}
+
+pub fn is_incognito() -> bool {
+    INCOGNITO.load(Ordering::Relaxed)
+}
+
+/// Stores the screenshot in the history.
+///
+/// # Return value
+/// `None` if the screenshot has been skipped because of the incognito mode.
+pub fn add(image: &DynamicImage, metadata: Metadata) -> anyhow::Result<Option<Entry>> {
+    if INCOGNITO.swap(false, Ordering::Relaxed) {
+        crate::tray::refresh();
+        return Ok(None);
+    }
+
+    get_xdg()
+        .create_data_directory(HISTORY_DIR)
+        .context("Couldn't create the history directory")?;
+
+    let entry = Entry {
+        id: metadata.time.format("%Y%m%d-%H%M%S-%3f").to_string(),
+        metadata,
+    };
+
+    image
+        .save(entry.image_path())
+        .context("Couldn't store the screenshot in the history")?;
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save(entry.thumbnail_path())
+        .context("Couldn't store the thumbnail in the history")?;
+    std::fs::write(
+        entry.metadata_path(),
+        serde_json::to_string_pretty(&entry.metadata)?,
+    )
+    .context("Couldn't store the metadata in the history")?;
+
+    Ok(Some(entry))
+}
+
+/// Like [`add`], but the screenshot is encoded in a new thread since that takes a while.
+/// Failures are only logged.
+pub fn add_in_background(image: DynamicImage, metadata: Metadata) {
+    let mut writing = WRITING.lock().unwrap();
+    writing.retain(|thread| !thread.is_finished());
+
+    writing.push(std::thread::spawn(move || {
+        if let Err(err) = add(&image, metadata) {
+            warn!("{:#}", err);
+        }
+    }));
+}
+
+/// Blocks until all screenshots of [`add_in_background`] are stored.
+///
+/// Should be called before the process exits, otherwise the screenshots might be incomplete.
+pub fn wait_for_writing() {
+    let writing = std::mem::take(&mut *WRITING.lock().unwrap());
+
+    for thread in writing {
+        let _ = thread.join();
+    }
+}
+
+/// Removes all entries which violate the retention policy of the config.
+pub fn prune(config: &config::History) -> anyhow::Result<PruneReport> {
+    let entries = entries();
+    let sizes: Vec<u64> = entries.iter().map(Entry::size).collect();
+    let times: Vec<DateTime<Local>> = entries.iter().map(|entry| entry.metadata.time).collect();
+
+    let mut report = PruneReport::default();
+    for index in get_expired(&times, &sizes, config, Local::now()) {
+        entries[index].delete()?;
+
+        report.removed += 1;
+        report.freed += sizes[index];
+    }
+
+    Ok(report)
+}
+
+/// Returns the indices of the entries which have to be removed.
+///
+/// The entries have to be sorted from the newest to the oldest one. Once an entry exceeds one
+/// of the limits, all older entries are removed as well.
+fn get_expired(
+    times: &[DateTime<Local>],
+    sizes: &[u64],
+    config: &config::History,
+    now: DateTime<Local>,
+) -> Vec<usize> {
+    let max_age = config.max_age.map(|days| Duration::days(days as i64));
+    let max_size = config.max_size.map(|mib| mib * 1024 * 1024);
+
+    let mut total_size = 0;
+    let first_expired = times.iter().zip(sizes).position(|(time, size)| {
+        total_size += size;
+
+        max_age.is_some_and(|max_age| now - *time > max_age)
+            || max_size.is_some_and(|max_size| total_size > max_size)
+    });
+
+    let keep = first_expired
+        .unwrap_or(times.len())
+        .min(config.max_count.unwrap_or(usize::MAX));
+
+    (keep..times.len()).collect()
+}
+
+/// Returns all entries of the history, the newest one first.
+pub fn entries() -> Vec<Entry> {
+    let Ok(dir) = std::fs::read_dir(get_history_dir_path()) else {
+        return vec![];
+    };
+
+    let mut entries: Vec<Entry> = dir
+        .filter_map(|file| file.ok())
+        .map(|file| file.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| match load_entry(&path) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!("Skipping history entry: {:#}", err);
+                None
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.metadata.time.cmp(&a.metadata.time));
+    entries
+}
+
+fn load_entry(metadata_path: &Path) -> anyhow::Result<Entry> {
+    let id = metadata_path
+        .file_stem()
+        .context("The metadata file doesn't have a name")?
+        .to_string_lossy()
+        .to_string();
+
+    let content = std::fs::read_to_string(metadata_path)
+        .with_context(|| format!("Couldn't read {}", metadata_path.to_string_lossy()))?;
+    let metadata = serde_json::from_str(&content)
+        .with_context(|| format!("Couldn't parse {}", metadata_path.to_string_lossy()))?;
+
+    Ok(Entry { id, metadata })
+}
+
+fn get_history_dir_path() -> PathBuf {
+    get_xdg().get_data_home().join(HISTORY_DIR)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local};
+
+    use crate::config;
+
+    use super::get_expired;
+
+    #[test]
+    fn test_get_expired() {
+        let now = Local::now();
+        let times = [now, now - Duration::days(2), now - Duration::days(10)];
+        let sizes = [1024 * 1024, 2 * 1024 * 1024, 1024];
+
+        let policy = |max_age, max_size, max_count| config::History {
+            enabled: true,
+            max_age,
+            max_size,
+            max_count,
+        };
+
+        assert_eq!(
+            get_expired(&times, &sizes, &policy(None, None, None), now),
+            vec![]
+        );
+        assert_eq!(
+            get_expired(&times, &sizes, &policy(Some(5), None, None), now),
+            vec![2]
+        );
+        assert_eq!(
+            get_expired(&times, &sizes, &policy(None, Some(2), None), now),
+            vec![1, 2]
+        );
+        assert_eq!(
+            get_expired(&times, &sizes, &policy(None, None, Some(1)), now),
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Runs the hook commands of the config after a screenshot has been saved, copied or uploaded.
+//!
+//! The details of the screenshot are passed through environment variables:
+//!
+//! - `FLAKESHOT_ACTION`: `save`, `copy` or `upload`
+//! - `FLAKESHOT_PATH`: the path of the saved file (only for `save`)
+//! - `FLAKESHOT_URL`: the URL of the uploaded file (only for `upload`)
+//! - `FLAKESHOT_WIDTH` and `FLAKESHOT_HEIGHT`
+//! - `FLAKESHOT_OUTPUTS`: the names of the outputs in the screenshot, separated by commas
+//!
+//! Hooks with `stdin = true` receive the same details as JSON document on stdin.
+use std::{
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    process::{Child, Stdio},
+    sync::Mutex,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::{config::Hook, history::Destination};
+
+/// How often we check if a hook has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The threads of the hooks which are still running.
+static RUNNING: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookAction {
+    Save,
+    Copy,
+    Upload,
+}
+
+impl HookAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Save => "save",
+            Self::Copy => "copy",
+            Self::Upload => "upload",
+        }
+    }
+}
+
+/// The details of a screenshot which are passed to the hooks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Event {
+    pub action: HookAction,
+    pub path: Option<PathBuf>,
+    pub url: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub outputs: Vec<String>,
+}
+
+impl Event {
+    pub fn new(destination: &Destination, width: u32, height: u32, outputs: Vec<String>) -> Self {
+        let (action, path, url) = match destination {
+            Destination::Clipboard => (HookAction::Copy, None, None),
+            Destination::File(path) => (HookAction::Save, Some(path.clone()), None),
+            Destination::Upload(url) => (HookAction::Upload, None, Some(url.clone())),
+        };
+
+        Self {
+            action,
+            path,
+            url,
+            width,
+            height,
+            outputs,
+        }
+    }
+
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("FLAKESHOT_ACTION", self.action.as_str().to_string()),
+            ("FLAKESHOT_WIDTH", self.width.to_string()),
+            ("FLAKESHOT_HEIGHT", self.height.to_string()),
+            ("FLAKESHOT_OUTPUTS", self.outputs.join(",")),
+        ];
+
+        if let Some(path) = &self.path {
+            env.push(("FLAKESHOT_PATH", path.to_string_lossy().to_string()));
+        }
+        if let Some(url) = &self.url {
+            env.push(("FLAKESHOT_URL", url.clone()));
+        }
+
+        env
+    }
+}
+
+/// Runs the hooks which are interested in the action of the event in the background.
+///
+/// `on_failure` is called with an error message for every hook which fails.
+pub fn run<F>(hooks: &[Hook], event: &Event, on_failure: F)
+where
+    F: Fn(String) + Clone + Send + 'static,
+{
+    let mut running = RUNNING.lock().unwrap();
+    running.retain(|thread| !thread.is_finished());
+
+    for hook in hooks
+        .iter()
+        .filter(|hook| hook.on.is_empty() || hook.on.contains(&event.action))
+    {
+        let hook = hook.clone();
+        let event = event.clone();
+        let on_failure = on_failure.clone();
+
+        running.push(std::thread::spawn(move || {
+            if let Err(err) = run_hook(&hook, &event) {
+                let msg = format!("The hook '{}' failed: {:#}", hook.command.join(" "), err);
+                error!("{}", msg);
+                on_failure(msg);
+            }
+        }));
+    }
+}
+
+/// Blocks until all hooks have finished.
+///
+/// Should be called before the process exits, otherwise the timeouts of the hooks can't be
+/// enforced.
+pub fn wait_for_running() {
+    let running = std::mem::take(&mut *RUNNING.lock().unwrap());
+
+    for thread in running {
+        let _ = thread.join();
+    }
+}
+
+fn run_hook(hook: &Hook, event: &Event) -> anyhow::Result<()> {
+    let (program, args) = hook
+        .command
+        .split_first()
+        .context("The command is empty.")?;
+    debug!("Running hook '{}'", hook.command.join(" "));
+
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .envs(event.env())
+        .stdin(if hook.stdin {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .spawn()
+        .with_context(|| format!("Couldn't spawn '{}'", program))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let json = serde_json::to_vec(event)?;
+
+        // the hook doesn't have to read the document
+        match stdin.write_all(&json) {
+            Err(err) if err.kind() != ErrorKind::BrokenPipe => {
+                return Err(err).context("Couldn't write the details into stdin");
+            }
+            _ => {}
+        }
+        // closing stdin tells the hook that the document is complete
+    }
+
+    wait_with_timeout(&mut child, Duration::from_secs(hook.timeout))
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> anyhow::Result<()> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                bail!("It exited with {}.", status);
+            }
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("It has been killed after {} seconds.", timeout.as_secs());
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(script: &str) -> Hook {
+        Hook {
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            on: vec![],
+            stdin: false,
+            timeout: 5,
+        }
+    }
+
+    fn event() -> Event {
+        Event::new(
+            &Destination::File(PathBuf::from("/tmp/shot.png")),
+            640,
+            480,
+            vec!["DP-1".to_string(), "HDMI-1".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_hooks_get_details_from_env() {
+        let hook = hook(concat!(
+            r#"test "$FLAKESHOT_ACTION" = save && test "$FLAKESHOT_PATH" = /tmp/shot.png && "#,
+            r#"test "$FLAKESHOT_WIDTH" = 640 && test "$FLAKESHOT_HEIGHT" = 480 && "#,
+            r#"test "$FLAKESHOT_OUTPUTS" = DP-1,HDMI-1 && test -z "$FLAKESHOT_URL""#
+        ));
+
+        run_hook(&hook, &event()).unwrap();
+    }
+
+    #[test]
+    fn test_hooks_get_details_from_stdin() {
+        let hook = Hook {
+            stdin: true,
+            ..hook(r#"grep -q '"action":"save"'"#)
+        };
+
+        run_hook(&hook, &event()).unwrap();
+    }
+
+    #[test]
+    fn test_failing_hooks_are_reported() {
+        assert!(run_hook(&hook("exit 3"), &event()).is_err());
+    }
+
+    #[test]
+    fn test_hooks_are_killed_after_timeout() {
+        let hook = Hook {
+            timeout: 0,
+            ..hook("sleep 5")
+        };
+
+        let start = Instant::now();
+        assert!(run_hook(&hook, &event()).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,21 +1,42 @@
 //! Welcome to the code-documentation of flakeshot!
 
-use std::{fs::File, path::PathBuf, sync::OnceLock};
-
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::Context;
+use backend::CaptureTarget;
 use clap::crate_name;
 use cli::LogLevel;
 use frontend::window::main_window::{AppModel, Settings};
 use gtk4::{gdk::Display, CssProvider};
+use image::DynamicImage;
 use relm4::RelmApp;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::EnvFilter;
 use xdg::BaseDirectories;
 
+pub mod autosave;
 pub mod backend;
 pub mod cli;
+pub mod clipboard;
 pub mod config;
+pub mod export;
+pub mod external_editor;
 pub mod frontend;
+pub mod history;
+pub mod hooks;
+pub mod pipeline;
+pub mod project;
+pub mod recent;
+pub mod recorder;
+pub mod save;
+pub mod timelapse;
 pub mod tray;
+pub mod upload;
 
 static XDG: OnceLock<BaseDirectories> = OnceLock::new();
 
@@ -76,6 +97,67 @@ fn get_default_config_path() -> PathBuf {
         .unwrap_or_else(|e| panic!("Couldn't access config file path: {}", e))
 }
 
+/// Takes a screenshot of the target and stores it at the given path without any user interaction.
+///
+/// The screenshot is written to stdout in the export format of the config if the path is `-`.
+pub fn capture_to_file(
+    target: &CaptureTarget,
+    path: &Path,
+    options: &config::Export,
+) -> anyhow::Result<()> {
+    let image = DynamicImage::from(backend::capture(target)?);
+
+    if path == Path::new("-") {
+        return write_to_stdout(&image, options);
+    }
+
+    export::save(&image, path, options)?;
+
+    tray::dbus::emit_screenshot_saved(path);
+    if let Err(err) = recent::add(path) {
+        tracing::warn!("{:#}", err);
+    }
+
+    Ok(())
+}
+
+/// Writes the image in the export format to stdout.
+pub fn write_to_stdout(image: &DynamicImage, options: &config::Export) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    export::encode(image, options.format, options, &mut stdout)?;
+    stdout
+        .flush()
+        .context("Couldn't write the screenshot to stdout")
+}
+
+/// Reads the image at the given path or from stdin if the path is `-`.
+pub fn open_image(path: &Path) -> anyhow::Result<DynamicImage> {
+    if path != Path::new("-") {
+        return image::open(path)
+            .with_context(|| format!("Couldn't open {}", path.to_string_lossy()));
+    }
+
+    let mut data = vec![];
+    std::io::stdin()
+        .lock()
+        .read_to_end(&mut data)
+        .context("Couldn't read the image from stdin")?;
+
+    image::load_from_memory(&data).context("Couldn't decode the image from stdin")
+}
+
+/// Opens the file with the default application of the user.
+pub fn open_with_default_app(path: &Path) -> anyhow::Result<()> {
+    let mut child = std::process::Command::new("xdg-open")
+        .arg(path)
+        .spawn()
+        .context("Couldn't spawn 'xdg-open'")?;
+
+    // reap the process once it's done so it doesn't stay around as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
 pub fn start(payload: Settings) {
     let app = RelmApp::new("org.flakeshot.app")
         .with_args(vec![])
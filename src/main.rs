@@ -1,10 +1,18 @@
 #[cfg(not(target_family = "unix"))]
 compile_error!("flakeshot only runs on UNIX-like systems.");
 
+use std::path::Path;
+
 use clap::Parser;
-use flakeshot::cli::{Cli, Command};
+use flakeshot::cli::{Cli, Command, GuiArgs, HistoryCommand, RecordArgs};
+use flakeshot::config::Config;
+use flakeshot::frontend::ui::ui_manager;
 use flakeshot::frontend::window::main_window::Settings;
 use flakeshot::frontend::window::run_mode::RunMode;
+use flakeshot::tray::{
+    control::{self, Request},
+    error::Error,
+};
 use notify_rust::Notification;
 
 fn main() {
@@ -12,15 +20,87 @@ fn main() {
     let cli = Cli::parse();
     flakeshot::init_logging(&cli.log_level, &cli.log_path);
 
-    if cli.command() == Command::PrintDefaultConfig {
-        flakeshot::config::print_default_config();
-        return;
+    let command = cli.command();
+
+    // let the daemon handle the command if it's already running since it's way faster
+    if let Some(request) = Request::from_command(&command, &cli.config) {
+        match control::send(&request) {
+            Ok(msg) => {
+                if !msg.is_empty() {
+                    println!("{}", msg);
+                }
+                return;
+            }
+            Err(Error::NotRunning) => tracing::debug!("No daemon is running."),
+            Err(err) => exit_with_error(err),
+        }
     }
 
+    match &command {
+        Command::PrintDefaultConfig => {
+            flakeshot::config::print_default_config();
+            return;
+        }
+        Command::Capture(args) => {
+            let options = load_config(&cli.config).export;
+
+            if let Err(err) = flakeshot::capture_to_file(&args.target(), &args.path, &options) {
+                exit_with_error(format!("{:#}", err));
+            }
+            return;
+        }
+        Command::History(HistoryCommand::Prune) => {
+            match flakeshot::history::prune(&load_config(&cli.config).history) {
+                Ok(report) => println!(
+                    "Removed {} screenshot(s) ({} KiB).",
+                    report.removed,
+                    report.freed / 1024
+                ),
+                Err(err) => exit_with_error(format!("{:#}", err)),
+            }
+            return;
+        }
+        Command::ServeClipboard => {
+            if let Err(err) = flakeshot::clipboard::serve_from_stdin() {
+                exit_with_error(format!("{:#}", err));
+            }
+            return;
+        }
+        Command::Record(RecordArgs { stop: true, .. }) => exit_with_error(Error::NotRunning),
+        Command::Tray if control::is_daemon_running() => exit_with_error(Error::AlreadyRunning),
+        _ => {}
+    }
+
+    let stdout_mode = matches!(&command, Command::Gui(GuiArgs { stdout: true, .. }));
+
     flakeshot::start(Settings {
-        run_mode: RunMode::from(cli.command()),
+        run_mode: RunMode::from(command),
         config_path: cli.config,
     });
+
+    flakeshot::hooks::wait_for_running();
+    flakeshot::history::wait_for_writing();
+    flakeshot::external_editor::wait_for_watchers();
+
+    // scripts have to know if there's an image on stdout or if the user cancelled
+    if stdout_mode && !ui_manager::is_confirmed() {
+        std::process::exit(1);
+    }
+}
+
+/// Loads the config of the user. The default config is only used if the file doesn't exist,
+/// a broken config is an error.
+fn load_config(path: &Path) -> Config {
+    if path.exists() {
+        Config::load(path).unwrap_or_else(|err| exit_with_error(err))
+    } else {
+        Config::default()
+    }
+}
+
+fn exit_with_error(err: impl std::fmt::Display) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
 }
 
 fn trace_panics() {
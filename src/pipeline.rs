@@ -0,0 +1,166 @@
+//! Pipelines are sequences of actions which are bound to keys of the editor, for example
+//! `"ctrl+o" = ["save", "open"]`.
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{clipboard, config::Config, export, get_xdg, history::Destination, save};
+
+/// The file which is used by `open` and `run` if the pipeline didn't save the image before.
+const TEMP_FILENAME: &str = "pipeline.png";
+
+/// A single action of a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PipelineStep {
+    /// Store the image in the quick save directory.
+    Save,
+
+    /// Copy the image into the clipboard.
+    Clipboard,
+
+    /// Open the image in the default image viewer.
+    Open,
+
+    /// Run a command and wait for it, for example `{ run = ["optipng", "{file}"] }`.
+    /// `{file}` is replaced by the path of the image.
+    Run(Vec<String>),
+}
+
+/// Runs the steps in order. The pipeline stops at the first step which fails.
+///
+/// `output` is used for the filename template of `save` and `on_exported` is called for every
+/// step which saved or copied the image.
+pub fn run(
+    steps: &[PipelineStep],
+    config: &Config,
+    image: &DynamicImage,
+    output: &str,
+    on_exported: impl Fn(&DynamicImage, Destination),
+) -> anyhow::Result<()> {
+    // the file of the last `save` step
+    let mut file: Option<PathBuf> = None;
+
+    for step in steps {
+        match step {
+            PipelineStep::Save => {
+                let path = save::quick_save(config, image, output)?;
+                on_exported(image, Destination::File(path.clone()));
+                file = Some(path);
+            }
+            PipelineStep::Clipboard => {
+                clipboard::copy_image(config, image)?;
+                on_exported(image, Destination::Clipboard);
+            }
+            PipelineStep::Open => {
+                let path = get_file(&mut file, config, image)?;
+                crate::open_with_default_app(&path)?;
+            }
+            PipelineStep::Run(command) => {
+                let path = get_file(&mut file, config, image)?;
+                run_command(command, &path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the file of the image and creates a temporary one if it hasn't been saved yet.
+fn get_file(
+    file: &mut Option<PathBuf>,
+    config: &Config,
+    image: &DynamicImage,
+) -> anyhow::Result<PathBuf> {
+    if let Some(path) = file {
+        return Ok(path.clone());
+    }
+
+    let path = get_xdg()
+        .place_runtime_file(TEMP_FILENAME)
+        .context("Couldn't create the temporary file of the pipeline")?;
+    export::save(image, &path, &config.export)?;
+
+    *file = Some(path.clone());
+    Ok(path)
+}
+
+fn run_command(command: &[String], path: &Path) -> anyhow::Result<()> {
+    let (program, args) = command.split_first().context("The command is empty.")?;
+    let path = path.to_string_lossy();
+
+    let status = std::process::Command::new(program)
+        .args(args.iter().map(|arg| arg.replace("{file}", &path)))
+        .status()
+        .with_context(|| format!("Couldn't spawn '{}'", program))?;
+
+    if !status.success() {
+        bail!("'{}' exited with {}", command.join(" "), status);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::Path};
+
+    use super::{run_command, PipelineStep};
+
+    fn to_command(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_run_command_replaces_file() {
+        let command = to_command(&[
+            "sh",
+            "-c",
+            r#"test "$1" = /tmp/shot.png && test "$2" = --out=/tmp/shot.png"#,
+            "sh",
+            "{file}",
+            "--out={file}",
+        ]);
+
+        run_command(&command, Path::new("/tmp/shot.png")).unwrap();
+    }
+
+    #[test]
+    fn test_run_command_fails_with_exit_code() {
+        let command = to_command(&["sh", "-c", "exit 3"]);
+
+        let err = run_command(&command, Path::new("/tmp/shot.png")).unwrap_err();
+        assert!(err.to_string().contains("exited with"), "{}", err);
+    }
+
+    #[test]
+    fn test_run_command_fails_without_program() {
+        assert!(run_command(&[], Path::new("/tmp/shot.png")).is_err());
+    }
+
+    #[test]
+    fn test_parse_pipelines() {
+        let pipelines: BTreeMap<String, Vec<PipelineStep>> = toml::from_str(
+            r#"
+            enter = ["save", "clipboard"]
+            "ctrl+o" = ["save", { run = ["optipng", "{file}"] }, "open"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pipelines["enter"],
+            [PipelineStep::Save, PipelineStep::Clipboard]
+        );
+        assert_eq!(
+            pipelines["ctrl+o"],
+            [
+                PipelineStep::Save,
+                PipelineStep::Run(vec!["optipng".to_string(), "{file}".to_string()]),
+                PipelineStep::Open,
+            ]
+        );
+    }
+}
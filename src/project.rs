@@ -0,0 +1,131 @@
+//! Stores the screenshot together with its annotations (`.flakeshot`), so they stay editable.
+//!
+//! A project is a JSON file which contains the capture without any annotations as base64
+//! encoded PNG, the crop rectangle and the drawables in the order they are drawn.
+use std::{io::Cursor, path::Path};
+
+use anyhow::Context;
+use base64::Engine;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::frontend::{shape::rectangle::Rectangle, ui::drawable::SerializedDrawable};
+
+/// The extension of project files.
+pub const EXTENSION: &str = "flakeshot";
+
+/// The version of the format. It's increased whenever old projects can't be read anymore.
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Project {
+    /// The capture without any drawables.
+    pub capture: RgbaImage,
+    pub selection: Rectangle,
+    pub drawables: Vec<SerializedDrawable>,
+}
+
+/// The representation of [`Project`] in the file.
+#[derive(Serialize, Deserialize)]
+struct ProjectFile {
+    version: u32,
+    capture: String,
+    selection: Rectangle,
+    drawables: Vec<SerializedDrawable>,
+}
+
+/// Returns `true` if the path has the extension of projects.
+pub fn is_project(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(EXTENSION))
+}
+
+impl Project {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.to_string_lossy()))?;
+
+        Self::from_json(&content)
+            .with_context(|| format!("{} is no valid project", path.to_string_lossy()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("Couldn't write {}", path.to_string_lossy()))
+    }
+
+    fn from_json(content: &str) -> anyhow::Result<Self> {
+        let file: ProjectFile = serde_json::from_str(content)?;
+
+        if file.version != VERSION {
+            anyhow::bail!(
+                "The project has version {}, but only version {} is supported",
+                file.version,
+                VERSION
+            );
+        }
+
+        let png = base64::engine::general_purpose::STANDARD
+            .decode(file.capture)
+            .context("Couldn't decode the capture")?;
+        let capture = image::load_from_memory_with_format(&png, ImageFormat::Png)
+            .context("Couldn't decode the capture")?
+            .to_rgba8();
+
+        Ok(Self {
+            capture,
+            selection: file.selection,
+            drawables: file.drawables,
+        })
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        let mut png = Vec::new();
+        DynamicImage::from(self.capture.clone())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .context("Couldn't encode the capture")?;
+
+        let file = ProjectFile {
+            version: VERSION,
+            capture: base64::engine::general_purpose::STANDARD.encode(png),
+            selection: self.selection,
+            drawables: self.drawables.clone(),
+        };
+
+        Ok(serde_json::to_string(&file)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use crate::frontend::{shape::point::Point, ui::tool::crop::CropDrawable};
+
+    use super::*;
+
+    #[test]
+    fn test_project_survives_a_round_trip() {
+        let selection = Rectangle {
+            fst: Point { x: 1.0, y: 2.0 },
+            snd: Point { x: 3.5, y: 4.0 },
+        };
+        let project = Project {
+            capture: RgbaImage::from_pixel(4, 3, Rgba([10, 20, 30, 255])),
+            selection,
+            drawables: vec![SerializedDrawable::Crop(CropDrawable { selection })],
+        };
+
+        let json = project.to_json().unwrap();
+        assert!(json.contains(r#""type":"crop""#));
+        assert_eq!(Project::from_json(&json).unwrap(), project);
+    }
+
+    #[test]
+    fn test_projects_are_detected_by_their_extension() {
+        assert!(is_project(Path::new("/tmp/bug.flakeshot")));
+        assert!(is_project(Path::new("BUG.FLAKESHOT")));
+        assert!(!is_project(Path::new("bug.png")));
+        assert!(!is_project(Path::new("flakeshot")));
+    }
+}
@@ -0,0 +1,47 @@
+//! Remembers the last saved screenshots for the "Recent" menu of the tray.
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::get_xdg;
+
+const RECENT_FILENAME: &str = "recent.json";
+
+/// How many screenshots are remembered.
+pub const MAX_ENTRIES: usize = 10;
+
+/// Puts the path at the front of the list and refreshes the menu of the tray.
+pub fn add(path: &Path) -> anyhow::Result<()> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Couldn't resolve {}", path.to_string_lossy()))?;
+
+    let mut entries = load();
+    entries.retain(|entry| entry != &path);
+    entries.insert(0, path);
+    entries.truncate(MAX_ENTRIES);
+
+    let file = get_xdg()
+        .place_state_file(RECENT_FILENAME)
+        .context("Couldn't access the list of recent screenshots")?;
+    std::fs::write(file, serde_json::to_string(&entries)?)
+        .context("Couldn't store the list of recent screenshots")?;
+
+    crate::tray::refresh();
+    Ok(())
+}
+
+/// Returns the recently saved screenshots (newest first) which still exist.
+pub fn load() -> Vec<PathBuf> {
+    let Some(file) = get_xdg().find_state_file(RECENT_FILENAME) else {
+        return vec![];
+    };
+
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<PathBuf>>(&content).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect()
+}
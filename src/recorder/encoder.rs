@@ -0,0 +1,308 @@
+//! Encodes the frames of a recording into an animated image.
+//!
+//! The frames are written as soon as they arrive, so only a single frame has to be kept in
+//! memory. The headers which depend on the number of frames are patched at the end.
+use std::{
+    io::{Seek, SeekFrom, Write},
+    time::Duration,
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use image::{
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        webp::WebPEncoder,
+    },
+    Delay, ExtendedColorType, RgbaImage,
+};
+use serde::{Deserialize, Serialize};
+
+/// The lower the value, the better the quality but the slower the encoding. (Range: 1-30)
+const GIF_SPEED: i32 = 10;
+
+/// The offset of the `acTL` chunk in an APNG: signature (8 bytes) + `IHDR` chunk (25 bytes).
+const APNG_ACTL_OFFSET: u64 = 33;
+
+/// All animated image formats which a recording can be stored in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Apng,
+    Webp,
+}
+
+impl AnimationFormat {
+    /// Returns the file extension (without the leading dot) of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// A single frame of a recording.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub image: RgbaImage,
+
+    /// How long the frame should be displayed.
+    pub delay: Duration,
+}
+
+/// Encodes the frames in the given format and writes the result into `writer`. The frames are
+/// pulled one by one, so they can be recorded while they are encoded.
+///
+/// # Invariant
+/// All frames must have the same dimensions.
+pub fn encode<W: Write + Seek>(
+    frames: impl IntoIterator<Item = anyhow::Result<Frame>>,
+    format: AnimationFormat,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let mut frames = frames.into_iter();
+    let first = frames
+        .next()
+        .context("The recording doesn't contain any frames.")??;
+    let dimensions = first.image.dimensions();
+    let frames = std::iter::once(Ok(first)).chain(frames);
+
+    match format {
+        AnimationFormat::Gif => encode_gif(frames, writer),
+        AnimationFormat::Apng => encode_apng(frames, dimensions, writer),
+        AnimationFormat::Webp => encode_webp(frames, dimensions, writer),
+    }
+}
+
+fn encode_gif<W: Write>(
+    frames: impl Iterator<Item = anyhow::Result<Frame>>,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let mut encoder = GifEncoder::new_with_speed(&mut *writer, GIF_SPEED);
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    for frame in frames {
+        let frame = frame?;
+
+        encoder
+            .encode_frame(image::Frame::from_parts(
+                frame.image,
+                0,
+                0,
+                Delay::from_saturating_duration(frame.delay),
+            ))
+            .context("Couldn't encode the gif")?;
+    }
+
+    // the trailer is written once the encoder is dropped
+    drop(encoder);
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// The number of frames isn't known in advance, so the `acTL` chunk is rewritten at the end.
+fn encode_apng<W: Write + Seek>(
+    frames: impl Iterator<Item = anyhow::Result<Frame>>,
+    (width, height): (u32, u32),
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let start = writer.stream_position()?;
+
+    let mut encoder = png::Encoder::new(&mut *writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // a placeholder which is never reached, otherwise the frame control would stop too early
+    encoder.set_animated(u32::MAX, 0)?;
+
+    let mut png_writer = encoder.write_header()?;
+    let mut frame_count: u32 = 0;
+    for frame in frames {
+        let frame = frame?;
+        let delay_ms = u16::try_from(frame.delay.as_millis()).unwrap_or(u16::MAX);
+
+        png_writer.set_frame_delay(delay_ms, 1000)?;
+        png_writer.write_image_data(frame.image.as_raw())?;
+        frame_count += 1;
+    }
+    png_writer.finish()?;
+
+    // num_frames + num_plays (0 = infinite)
+    let mut actl = Vec::with_capacity(12);
+    actl.extend_from_slice(b"acTL");
+    actl.extend_from_slice(&frame_count.to_be_bytes());
+    actl.extend_from_slice(&0u32.to_be_bytes());
+    let crc = crc32fast::hash(&actl);
+
+    let end = writer.stream_position()?;
+    // skip the length of the chunk since it didn't change
+    writer.seek(SeekFrom::Start(start + APNG_ACTL_OFFSET + 4))?;
+    writer.write_all(&actl)?;
+    writer.write_all(&crc.to_be_bytes())?;
+    writer.seek(SeekFrom::Start(end))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// The encoder of `image` can only create still images, so each frame is encoded on its own
+/// and the resulting bitstreams are wrapped into the extended (animated) WebP container.
+///
+/// See: <https://developers.google.com/speed/webp/docs/riff_container#animation>
+fn encode_webp<W: Write + Seek>(
+    frames: impl Iterator<Item = anyhow::Result<Frame>>,
+    (width, height): (u32, u32),
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    const ANIMATION_FLAG: u8 = 1 << 1;
+    const ALPHA_FLAG: u8 = 1 << 4;
+    const DO_NOT_BLEND_FLAG: u8 = 1 << 1;
+
+    let start = writer.stream_position()?;
+
+    // the size of the RIFF container is patched at the end
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    writer.write_all(b"WEBP")?;
+
+    let mut chunk = Vec::new();
+
+    let mut vp8x = vec![ANIMATION_FLAG | ALPHA_FLAG, 0, 0, 0];
+    vp8x.extend_from_slice(&u24_bytes(width - 1));
+    vp8x.extend_from_slice(&u24_bytes(height - 1));
+    write_riff_chunk(&mut chunk, b"VP8X", &vp8x);
+
+    // background color (BGRA) + loop count (0 = infinite)
+    write_riff_chunk(&mut chunk, b"ANIM", &[0, 0, 0, 0, 0, 0]);
+    writer.write_all(&chunk)?;
+
+    for frame in frames {
+        let frame = frame?;
+
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still)
+            .encode(
+                frame.image.as_raw(),
+                width,
+                height,
+                ExtendedColorType::Rgba8,
+            )
+            .context("Couldn't encode webp frame")?;
+
+        // The still image is a "simple" webp: RIFF header (12 bytes) followed by the VP8L chunk
+        // which we can reuse as is.
+        let bitstream = still
+            .get(12..)
+            .filter(|chunk| chunk.starts_with(b"VP8L"))
+            .context("The webp encoder returned an unexpected container")?;
+
+        let delay_ms = u32::try_from(frame.delay.as_millis()).unwrap_or(u32::MAX);
+
+        let mut anmf = Vec::with_capacity(16 + bitstream.len());
+        anmf.extend_from_slice(&u24_bytes(0)); // x offset
+        anmf.extend_from_slice(&u24_bytes(0)); // y offset
+        anmf.extend_from_slice(&u24_bytes(width - 1));
+        anmf.extend_from_slice(&u24_bytes(height - 1));
+        anmf.extend_from_slice(&u24_bytes(delay_ms));
+        anmf.push(DO_NOT_BLEND_FLAG);
+        anmf.extend_from_slice(bitstream);
+
+        chunk.clear();
+        write_riff_chunk(&mut chunk, b"ANMF", &anmf);
+        writer.write_all(&chunk)?;
+    }
+
+    let end = writer.stream_position()?;
+    let riff_size = u32::try_from(end - start - 8).context("The recording is too big for webp")?;
+    writer.seek(SeekFrom::Start(start + 4))?;
+    writer.write_all(&riff_size.to_le_bytes())?;
+    writer.seek(SeekFrom::Start(end))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_riff_chunk(buffer: &mut Vec<u8>, name: &[u8; 4], payload: &[u8]) {
+    buffer.extend_from_slice(name);
+    buffer.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(payload);
+
+    // chunks are padded to an even size
+    if payload.len() % 2 == 1 {
+        buffer.push(0);
+    }
+}
+
+/// Returns the lower three bytes of `value` in little endian. Larger values are saturated.
+fn u24_bytes(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.min(0xFF_FF_FF).to_le_bytes();
+    [a, b, c]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use image::{
+        codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+        AnimationDecoder, Rgba, RgbaImage,
+    };
+
+    use super::{encode, AnimationFormat, Frame};
+
+    fn get_frames() -> Vec<anyhow::Result<Frame>> {
+        [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])]
+            .into_iter()
+            .map(|color| {
+                Ok(Frame {
+                    image: RgbaImage::from_pixel(5, 3, color),
+                    delay: Duration::from_millis(100),
+                })
+            })
+            .collect()
+    }
+
+    fn encode_to_vec(format: AnimationFormat) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        encode(get_frames(), format, &mut buffer).unwrap();
+        buffer.into_inner()
+    }
+
+    /// Makes sure that each format contains all recorded frames
+    #[test]
+    fn test_encode_keeps_all_frames() {
+        let gif = GifDecoder::new(Cursor::new(encode_to_vec(AnimationFormat::Gif))).unwrap();
+        assert_eq!(gif.into_frames().count(), 2);
+
+        let apng = PngDecoder::new(Cursor::new(encode_to_vec(AnimationFormat::Apng)))
+            .unwrap()
+            .apng()
+            .unwrap();
+        assert_eq!(apng.into_frames().count(), 2);
+
+        let webp = WebPDecoder::new(Cursor::new(encode_to_vec(AnimationFormat::Webp))).unwrap();
+        let frames = webp.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].buffer().get_pixel(4, 2), &Rgba([0, 0, 255, 255]));
+    }
+
+    /// The frame count of an APNG is only known at the end
+    #[test]
+    fn test_encode_apng_patches_frame_count() {
+        let decoder = png::Decoder::new(Cursor::new(encode_to_vec(AnimationFormat::Apng)));
+        let reader = decoder.read_info().unwrap();
+
+        let animation_control = reader.info().animation_control.unwrap();
+        assert_eq!(animation_control.num_frames, 2);
+        assert_eq!(animation_control.num_plays, 0);
+    }
+
+    #[test]
+    fn test_encode_without_frames_fails() {
+        assert!(encode(vec![], AnimationFormat::Gif, &mut Cursor::new(Vec::new())).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Records a region of the screen into an animated image.
+pub mod encoder;
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use notify_rust::Urgency;
+use relm4::Sender;
+
+use crate::{
+    backend::{FrameGrabber, Region},
+    cli::RecordArgs,
+    config,
+    frontend::window::{main_window::Command, notification::Notification},
+    get_xdg,
+};
+
+use self::encoder::{AnimationFormat, Frame};
+
+/// The subdirectory of the XDG data directory where recordings are stored by default.
+const RECORDINGS_DIR: &str = "recordings";
+
+/// Gives the compositor/xorg-server some time to remove our overlay windows from the screen.
+const START_DELAY: Duration = Duration::from_millis(200);
+
+/// How long we wait at most for a changed frame before checking if we should stop.
+const FRAME_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The settings of a single recording.
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    pub format: AnimationFormat,
+    pub fps: u32,
+    pub max_duration: Duration,
+    pub output: PathBuf,
+}
+
+impl RecordOptions {
+    /// Creates the options from the config where each value can be overwritten by the cli.
+    pub fn new(config: &config::Recording, args: Option<&RecordArgs>) -> anyhow::Result<Self> {
+        let format = args.and_then(|args| args.format).unwrap_or(config.format);
+        let fps = args.and_then(|args| args.fps).unwrap_or(config.fps).max(1);
+        let max_duration = args
+            .and_then(|args| args.duration)
+            .unwrap_or(config.max_duration);
+
+        let output = match args.and_then(|args| args.output.clone()) {
+            Some(output) => output,
+            None => get_default_output_path(format)?,
+        };
+
+        Ok(Self {
+            format,
+            fps,
+            max_duration: Duration::from_secs(max_duration),
+            output,
+        })
+    }
+}
+
+/// A handle to a running recording.
+#[derive(Debug)]
+pub struct Recording {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Recording {
+    /// Starts recording the given region in a new thread.
+    ///
+    /// Once the recording is stored (or failed), [`Command::RecordingFinished`] is sent.
+    pub fn start(region: Region, options: RecordOptions, sender: Sender<Command>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let stop = stop.clone();
+
+            std::thread::spawn(move || {
+                let notification = match record_to_file(region, &options, &stop) {
+                    Ok(()) => Notification {
+                        msg: format!("Recording saved to {}", options.output.to_string_lossy()),
+                        urgency: Urgency::Low,
+                    },
+                    Err(err) => Notification {
+                        msg: format!("Recording failed: {:#}", err),
+                        urgency: Urgency::Critical,
+                    },
+                };
+
+                sender
+                    .send(Command::Notify(notification))
+                    .expect("Couldn't send notification command");
+                sender
+                    .send(Command::RecordingFinished)
+                    .expect("Couldn't send recording finished command");
+            })
+        };
+
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Tells the recording to stop. The output file is finished afterwards.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        self.stop();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn record_to_file(
+    region: Region,
+    options: &RecordOptions,
+    stop: &AtomicBool,
+) -> anyhow::Result<()> {
+    std::thread::sleep(START_DELAY);
+
+    let frames = Frames::new(region, options, stop)?;
+
+    let file = File::create(&options.output).with_context(|| {
+        format!(
+            "Couldn't create the output file {}",
+            options.output.to_string_lossy()
+        )
+    })?;
+
+    let result = encoder::encode(frames, options.format, &mut BufWriter::new(file));
+    if result.is_err() {
+        // don't leave a broken recording behind
+        let _ = std::fs::remove_file(&options.output);
+    }
+
+    result
+}
+
+/// Captures the frames of the recording while they are encoded.
+///
+/// A frame is only yielded once the next (changed) frame arrived since its delay isn't known
+/// before, so at most one frame is kept in memory.
+struct Frames<'a> {
+    grabber: FrameGrabber,
+    stop: &'a AtomicBool,
+    max_duration: Duration,
+    frame_interval: Duration,
+
+    start: Instant,
+    last_capture: Instant,
+    pending: Option<Frame>,
+    done: bool,
+}
+
+impl<'a> Frames<'a> {
+    fn new(region: Region, options: &RecordOptions, stop: &'a AtomicBool) -> anyhow::Result<Self> {
+        let start = Instant::now();
+
+        Ok(Self {
+            grabber: FrameGrabber::new(region)?,
+            stop,
+            max_duration: options.max_duration,
+            frame_interval: Duration::from_secs(1) / options.fps,
+            start,
+            last_capture: start,
+            pending: None,
+            done: false,
+        })
+    }
+
+    /// Waits for the next changed frame and returns the previous one.
+    fn capture(&mut self) -> anyhow::Result<Option<Frame>> {
+        while !self.stop.load(Ordering::Relaxed) && self.start.elapsed() < self.max_duration {
+            let tick = Instant::now();
+
+            let mut previous = None;
+            if let Some(image) = self.grabber.next_frame(FRAME_TIMEOUT)? {
+                let now = Instant::now();
+
+                // a frame is displayed until the next (changed) frame arrives
+                previous = self.pending.replace(Frame {
+                    image,
+                    delay: self.frame_interval,
+                });
+                if let Some(previous) = &mut previous {
+                    previous.delay = now - self.last_capture;
+                }
+                self.last_capture = now;
+            }
+
+            if let Some(remaining) = self.frame_interval.checked_sub(tick.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+
+            if previous.is_some() {
+                return Ok(previous);
+            }
+        }
+
+        self.done = true;
+        Ok(self.pending.take().map(|mut last| {
+            last.delay = self.last_capture.elapsed().max(self.frame_interval);
+            last
+        }))
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = anyhow::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.capture() {
+            Ok(frame) => frame.map(Ok),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn get_default_output_path(format: AnimationFormat) -> anyhow::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs();
+
+    get_xdg()
+        .place_data_file(format!(
+            "{}/flakeshot-{}.{}",
+            RECORDINGS_DIR,
+            timestamp,
+            format.extension()
+        ))
+        .context("Couldn't access the recordings directory")
+}
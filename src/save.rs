@@ -0,0 +1,199 @@
+//! Stores screenshots in the configured directory without asking the user for a path.
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use chrono::{format::Item, format::StrftimeItems, DateTime, Local};
+use image::{DynamicImage, GenericImageView};
+
+use crate::{config::Config, export, recent, tray::dbus};
+
+/// The subdirectory of `XDG_PICTURES_DIR` which is used if no directory is configured.
+const SCREENSHOTS_DIR: &str = "Screenshots";
+
+/// The values of the placeholders of the filename template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateValues<'a> {
+    pub time: DateTime<Local>,
+    pub output: &'a str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Stores the image in the directory of the config.
+///
+/// # Return value
+/// The path of the new file.
+pub fn quick_save(config: &Config, image: &DynamicImage, output: &str) -> anyhow::Result<PathBuf> {
+    let directory = match &config.save.directory {
+        Some(directory) => directory.clone(),
+        None => get_default_directory()?,
+    };
+    std::fs::create_dir_all(&directory)
+        .with_context(|| format!("Couldn't create {}", directory.to_string_lossy()))?;
+
+    let (width, height) = image.dimensions();
+    let values = TemplateValues {
+        time: Local::now(),
+        output,
+        width,
+        height,
+    };
+    let path = get_free_path(&directory, &config.save.filename, &values)?;
+
+    export::save(image, &path, &config.export)?;
+
+    dbus::emit_screenshot_saved(&path);
+    if let Err(err) = recent::add(&path) {
+        tracing::warn!("{:#}", err);
+    }
+
+    Ok(path)
+}
+
+/// Returns the first path in the directory which doesn't exist yet.
+///
+/// `{n}` is incremented until a free filename is found. If the template doesn't contain `{n}`,
+/// `-<n>` is appended to the file stem instead once the first filename is taken.
+fn get_free_path(
+    directory: &Path,
+    template: &str,
+    values: &TemplateValues,
+) -> anyhow::Result<PathBuf> {
+    let filename = expand_template(template, values)?;
+
+    if !filename.contains("{n}") {
+        let path = directory.join(&filename);
+        if !path.exists() {
+            return Ok(path);
+        }
+    }
+
+    for n in 1.. {
+        let path = if filename.contains("{n}") {
+            directory.join(filename.replace("{n}", &n.to_string()))
+        } else {
+            let filename = Path::new(&filename);
+            let stem = filename.file_stem().unwrap_or_default().to_string_lossy();
+
+            match filename.extension() {
+                Some(ext) => directory.join(format!("{}-{}.{}", stem, n, ext.to_string_lossy())),
+                None => directory.join(format!("{}-{}", stem, n)),
+            }
+        };
+
+        if !path.exists() {
+            return Ok(path);
+        }
+    }
+
+    unreachable!("We ran out of numbers.")
+}
+
+/// Replaces all placeholders of the template except `{n}`.
+pub fn expand_template(template: &str, values: &TemplateValues) -> anyhow::Result<String> {
+    let template = template
+        .replace("{output}", values.output)
+        .replace("{width}", &values.width.to_string())
+        .replace("{height}", &values.height.to_string());
+
+    // chrono panics while formatting invalid specifiers
+    let items: Vec<Item> = StrftimeItems::new(&template).collect();
+    if items.contains(&Item::Error) {
+        return Err(anyhow!("The filename template '{}' is invalid.", template));
+    }
+
+    let filename = values.time.format_with_items(items.into_iter()).to_string();
+
+    if filename.contains('/') {
+        return Err(anyhow!(
+            "The filename '{}' mustn't contain a '/'.",
+            filename
+        ));
+    }
+
+    Ok(filename)
+}
+
+/// Returns `XDG_PICTURES_DIR/Screenshots`.
+pub fn get_default_directory() -> anyhow::Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("Couldn't find your home directory")?;
+
+    let pictures = std::env::var_os("XDG_PICTURES_DIR")
+        .map(PathBuf::from)
+        .or_else(|| {
+            let user_dirs = xdg::BaseDirectories::new()
+                .ok()?
+                .get_config_home()
+                .join("user-dirs.dirs");
+            let content = std::fs::read_to_string(user_dirs).ok()?;
+
+            parse_user_dirs(&content, "XDG_PICTURES_DIR", &home)
+        })
+        .unwrap_or_else(|| home.join("Pictures"));
+
+    Ok(pictures.join(SCREENSHOTS_DIR))
+}
+
+/// Looks up the directory in the content of `user-dirs.dirs`.
+///
+/// The lines have the form `XDG_PICTURES_DIR="$HOME/Pictures"`
+/// (see `man user-dirs.dirs`).
+fn parse_user_dirs(content: &str, name: &str, home: &Path) -> Option<PathBuf> {
+    let value = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == name)
+        .map(|(_, value)| value.trim().trim_matches('"'))?;
+
+    match value.strip_prefix("$HOME") {
+        Some(relative) => Some(home.join(relative.trim_start_matches('/'))),
+        None if value.starts_with('/') => Some(PathBuf::from(value)),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use chrono::{Local, TimeZone};
+
+    use super::{expand_template, parse_user_dirs, TemplateValues};
+
+    #[test]
+    fn test_expand_template() {
+        let values = TemplateValues {
+            time: Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap(),
+            output: "DP-1",
+            width: 1920,
+            height: 1080,
+        };
+
+        assert_eq!(
+            expand_template("%Y-%m-%d_%H%M_{output}_{width}x{height}_{n}.png", &values).unwrap(),
+            "2024-03-09_1405_DP-1_1920x1080_{n}.png"
+        );
+        assert!(expand_template("%Q.png", &values).is_err());
+        assert!(expand_template("%Y/{n}.png", &values).is_err());
+    }
+
+    #[test]
+    fn test_parse_user_dirs() {
+        let content = concat!(
+            "# This file is written by xdg-user-dirs-update\n",
+            "XDG_DESKTOP_DIR=\"$HOME/Desktop\"\n",
+            "XDG_PICTURES_DIR=\"$HOME/Bilder\"\n",
+        );
+        let home = Path::new("/home/flake");
+
+        assert_eq!(
+            parse_user_dirs(content, "XDG_PICTURES_DIR", home),
+            Some(PathBuf::from("/home/flake/Bilder"))
+        );
+        assert_eq!(parse_user_dirs(content, "XDG_MUSIC_DIR", home), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Takes a screenshot every few seconds and stores them as numbered files in a directory.
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use image::RgbaImage;
+use notify_rust::Urgency;
+use relm4::Sender;
+
+use crate::{
+    backend::{self, CaptureTarget},
+    cli::TimelapseArgs,
+    config,
+    frontend::window::{main_window::Command, notification::Notification},
+    get_xdg,
+};
+
+/// The subdirectory of the XDG data directory where timelapses are stored by default.
+const TIMELAPSES_DIR: &str = "timelapses";
+
+/// How often we check if the timelapse should be stopped while waiting for the next screenshot.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The settings of a single timelapse.
+#[derive(Debug, Clone)]
+pub struct TimelapseOptions {
+    pub interval: Duration,
+    pub directory: PathBuf,
+    pub max_count: Option<u32>,
+    pub max_duration: Option<Duration>,
+    pub skip_unchanged: bool,
+    pub target: CaptureTarget,
+}
+
+impl TimelapseOptions {
+    /// Creates the options from the config where each value can be overwritten by the cli.
+    pub fn new(config: &config::Timelapse, args: Option<&TimelapseArgs>) -> anyhow::Result<Self> {
+        let interval = args
+            .and_then(|args| args.interval)
+            .unwrap_or(config.interval)
+            .max(1);
+        let max_count = args.and_then(|args| args.max_count).or(config.max_count);
+        let max_duration = args
+            .and_then(|args| args.max_duration)
+            .or(config.max_duration);
+        let skip_unchanged = args
+            .and_then(|args| args.skip_unchanged())
+            .unwrap_or(config.skip_unchanged);
+
+        let output = args
+            .and_then(|args| args.output.clone())
+            .or_else(|| config.output.clone());
+        let region = args.and_then(|args| args.region).or(config.region);
+        let target = match (output, region) {
+            (Some(output), _) => CaptureTarget::Output(output),
+            (None, Some(region)) => CaptureTarget::Region(region),
+            (None, None) => CaptureTarget::Screen,
+        };
+
+        let directory = match args
+            .and_then(|args| args.directory.clone())
+            .or_else(|| config.directory.clone())
+        {
+            Some(directory) => directory,
+            None => get_default_directory()?,
+        };
+
+        Ok(Self {
+            interval: Duration::from_secs(interval),
+            directory,
+            max_count,
+            max_duration: max_duration.map(Duration::from_secs),
+            skip_unchanged,
+            target,
+        })
+    }
+}
+
+/// A handle to a running timelapse.
+#[derive(Debug)]
+pub struct Timelapse {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Timelapse {
+    /// Starts the timelapse in a new thread.
+    ///
+    /// Once the timelapse stopped (or failed), [`Command::TimelapseFinished`] is sent.
+    pub fn start(options: TimelapseOptions, sender: Sender<Command>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let stop = stop.clone();
+
+            std::thread::spawn(move || {
+                let notification = match run(&options, &stop) {
+                    Ok(count) => Notification {
+                        msg: format!(
+                            "Timelapse finished: {} screenshots saved to {}",
+                            count,
+                            options.directory.to_string_lossy()
+                        ),
+                        urgency: Urgency::Low,
+                    },
+                    Err(err) => Notification {
+                        msg: format!("Timelapse failed: {:#}", err),
+                        urgency: Urgency::Critical,
+                    },
+                };
+
+                sender
+                    .send(Command::Notify(notification))
+                    .expect("Couldn't send notification command");
+                sender
+                    .send(Command::TimelapseFinished)
+                    .expect("Couldn't send timelapse finished command");
+            })
+        };
+
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Tells the timelapse to stop after the current screenshot.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Timelapse {
+    fn drop(&mut self) {
+        self.stop();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Runs the timelapse until it's stopped or one of the limits is reached.
+///
+/// # Return value
+/// The amount of stored screenshots.
+fn run(options: &TimelapseOptions, stop: &AtomicBool) -> anyhow::Result<u32> {
+    std::fs::create_dir_all(&options.directory).with_context(|| {
+        format!(
+            "Couldn't create the directory {}",
+            options.directory.to_string_lossy()
+        )
+    })?;
+
+    let start = Instant::now();
+    let mut previous: Option<RgbaImage> = None;
+    let mut count = 0;
+    let mut file_number = 0;
+
+    while !stop.load(Ordering::Relaxed)
+        && options
+            .max_count
+            .map_or(true, |max_count| count < max_count)
+        && options
+            .max_duration
+            .map_or(true, |max_duration| start.elapsed() < max_duration)
+    {
+        let tick = Instant::now();
+        let image = backend::capture(&options.target)?;
+
+        let is_unchanged = previous.as_ref() == Some(&image);
+        if !(options.skip_unchanged && is_unchanged) {
+            // don't overwrite the screenshots of a previous timelapse in the same directory
+            let path = loop {
+                file_number += 1;
+
+                let path = options.directory.join(format!("{:05}.png", file_number));
+                if !path.exists() {
+                    break path;
+                }
+            };
+
+            image
+                .save(&path)
+                .with_context(|| format!("Couldn't save {}", path.to_string_lossy()))?;
+
+            count += 1;
+            previous = Some(image);
+        }
+
+        while !stop.load(Ordering::Relaxed) && tick.elapsed() < options.interval {
+            std::thread::sleep(
+                STOP_POLL_INTERVAL.min(options.interval.saturating_sub(tick.elapsed())),
+            );
+        }
+    }
+
+    Ok(count)
+}
+
+fn get_default_directory() -> anyhow::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs();
+
+    get_xdg()
+        .create_data_directory(format!("{}/{}", TIMELAPSES_DIR, timestamp))
+        .context("Couldn't access the timelapses directory")
+}
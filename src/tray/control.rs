@@ -0,0 +1,208 @@
+//! The control socket of the tray daemon.
+//!
+//! Other flakeshot invocations connect to the socket, send a single [`Request`] as one line of
+//! JSON and receive a single [`Response`] (also as one line of JSON) afterwards.
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+
+use relm4::Sender;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    net::UnixListener,
+};
+use tracing::{debug, error};
+
+use crate::{
+    backend::CaptureTarget,
+    cli::{self, GuiArgs, RecordArgs},
+    clipboard::Offer,
+    config::Config,
+    frontend::window::main_window::Command,
+    get_xdg,
+};
+
+use super::error::Error;
+
+const SOCKET_FILENAME: &str = "flakeshot.sock";
+
+/// All requests which can be sent to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Request {
+    /// Open the capture ui.
+    Gui,
+
+    /// Take a screenshot of the target and store it at the given (absolute) path. The export
+    /// options are taken from the config of the sender.
+    Capture {
+        target: CaptureTarget,
+        path: PathBuf,
+        config_path: PathBuf,
+    },
+
+    /// Stop the current recording.
+    StopRecording,
+
+    /// Serve the offers as clipboard content since the sender is going to exit.
+    SetClipboard(Vec<Offer>),
+}
+
+/// The answer of the daemon. `Ok` contains a message which can be shown to the user.
+pub type Response = Result<String, String>;
+
+impl Request {
+    /// Returns the request which should be sent instead of running the given command in this
+    /// process or `None` if the command can't be handled by the daemon.
+    pub fn from_command(command: &cli::Command, config_path: &Path) -> Option<Self> {
+        match command {
+            // the daemon only opens the default ui
+            cli::Command::Gui(args) if args == &GuiArgs::default() => Some(Self::Gui),
+            // the output of the daemon doesn't end up in our stdout
+            cli::Command::Capture(args) if args.path == Path::new("-") => None,
+            cli::Command::Capture(args) => {
+                // the daemon doesn't know our working directory
+                let current_dir = std::env::current_dir().ok()?;
+
+                Some(Self::Capture {
+                    target: args.target(),
+                    path: current_dir.join(&args.path),
+                    config_path: current_dir.join(config_path),
+                })
+            }
+            cli::Command::Record(RecordArgs { stop: true, .. }) => Some(Self::StopRecording),
+            _ => None,
+        }
+    }
+}
+
+/// Sends the request to the daemon and waits for its response.
+///
+/// # Return value
+/// [`Error::NotRunning`] if there's no daemon which could handle the request.
+pub fn send(request: &Request) -> Result<String, Error> {
+    let mut stream = UnixStream::connect(get_socket_path()?).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => Error::NotRunning,
+        _ => Error::IO(err),
+    })?;
+
+    let mut message = serde_json::to_string(request)?;
+    message.push('\n');
+    stream.write_all(message.as_bytes())?;
+
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+
+    let response: Response = serde_json::from_str(&answer)?;
+    response.map_err(Error::Daemon)
+}
+
+/// Returns `true` if a daemon is listening on the control socket.
+pub fn is_daemon_running() -> bool {
+    get_socket_path()
+        .map(|path| UnixStream::connect(path).is_ok())
+        .unwrap_or(false)
+}
+
+/// Creates the control socket.
+///
+/// # Invariant
+/// The caller has to hold the lock of the daemon, otherwise we might steal the socket of
+/// another daemon.
+pub fn bind() -> Result<UnixListener, Error> {
+    let path = get_socket_path()?;
+
+    // a crashed daemon leaves its socket behind
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Handles the clients of the control socket until the listener fails.
+pub async fn serve(listener: UnixListener, sender: Sender<Command>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let sender = sender.clone();
+
+                tokio::spawn(async move {
+                    if let Err(err) = handle_client(stream, sender).await {
+                        error!("Couldn't handle client of the control socket: {}", err);
+                    }
+                });
+            }
+            Err(err) => {
+                error!("Control socket stopped accepting clients: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_client(
+    stream: tokio::net::UnixStream,
+    sender: Sender<Command>,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader)
+        .read_line(&mut line)
+        .await?;
+
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => {
+            debug!("Received request: {:?}", request);
+            handle_request(request, &sender).await
+        }
+        Err(err) => Err(format!("Invalid request: {}", err)),
+    };
+
+    let mut answer = serde_json::to_string(&response)?;
+    answer.push('\n');
+    writer.write_all(answer.as_bytes()).await?;
+
+    Ok(())
+}
+
+async fn handle_request(request: Request, sender: &Sender<Command>) -> Response {
+    match request {
+        Request::Gui => send_command(sender, Command::Gui),
+        Request::StopRecording => send_command(sender, Command::StopRecording),
+        Request::Capture {
+            target,
+            path,
+            config_path,
+        } => tokio::task::spawn_blocking(move || {
+            let options = Config::load_or_default(&config_path).export;
+
+            crate::capture_to_file(&target, &path, &options)
+                .map(|()| format!("Screenshot saved to {}", path.to_string_lossy()))
+                .map_err(|err| format!("{:#}", err))
+        })
+        .await
+        .unwrap_or_else(|err| Err(format!("The capture task failed: {}", err))),
+        Request::SetClipboard(offers) => tokio::task::spawn_blocking(move || {
+            crate::clipboard::serve(offers)
+                .map(|()| String::new())
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .unwrap_or_else(|err| Err(format!("The clipboard task failed: {}", err))),
+    }
+}
+
+fn send_command(sender: &Sender<Command>, command: Command) -> Response {
+    sender
+        .send(command)
+        .map(|()| String::new())
+        .map_err(|_| "The daemon is shutting down.".to_string())
+}
+
+fn get_socket_path() -> Result<PathBuf, Error> {
+    Ok(get_xdg().place_runtime_file(SOCKET_FILENAME)?)
+}
@@ -0,0 +1,150 @@
+//! The D-Bus interface of the tray daemon.
+//!
+//! The daemon exports the `org.flakeshot.Flakeshot` interface at [`OBJECT_PATH`] on the session
+//! bus so window managers and status bars can trigger and observe flakeshot.
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use relm4::Sender;
+use tracing::error;
+use zbus::{dbus_interface, fdo, Connection, ConnectionBuilder, SignalContext};
+
+use crate::{
+    backend::{CaptureTarget, Region},
+    config::Config,
+    frontend::window::main_window::Command,
+};
+
+pub const BUS_NAME: &str = "org.flakeshot.Flakeshot";
+pub const OBJECT_PATH: &str = "/org/flakeshot/Flakeshot";
+
+/// The connection of the daemon. It's only set if the interface has been exported.
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+#[derive(Debug)]
+struct Service {
+    sender: Sender<Command>,
+    config_path: PathBuf,
+}
+
+#[dbus_interface(name = "org.flakeshot.Flakeshot")]
+impl Service {
+    /// Opens the capture ui.
+    async fn open_editor(&self) -> fdo::Result<()> {
+        self.sender
+            .send(Command::Gui)
+            .map_err(|_| fdo::Error::Failed("The daemon is shutting down.".to_string()))
+    }
+
+    /// Takes a screenshot of all outputs and stores it at the given path.
+    async fn capture_fullscreen(&self, path: String) -> fdo::Result<String> {
+        self.capture(CaptureTarget::Screen, path).await
+    }
+
+    /// Takes a screenshot of the given region and stores it at the given path.
+    async fn capture_region(
+        &self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        path: String,
+    ) -> fdo::Result<String> {
+        let region = Region {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        self.capture(CaptureTarget::Region(region), path).await
+    }
+
+    /// Takes a screenshot of the output with the given name and stores it at the given path.
+    async fn capture_output(&self, name: String, path: String) -> fdo::Result<String> {
+        self.capture(CaptureTarget::Output(name), path).await
+    }
+
+    /// Emitted after a screenshot has been stored in a file.
+    #[dbus_interface(signal)]
+    async fn screenshot_saved(ctxt: &SignalContext<'_>, path: &str) -> zbus::Result<()>;
+
+    /// Emitted after a screenshot has been copied into the clipboard.
+    #[dbus_interface(signal)]
+    async fn screenshot_copied(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+/// Exports the interface on the session bus.
+pub async fn start(sender: Sender<Command>, config_path: PathBuf) -> zbus::Result<()> {
+    let connection = ConnectionBuilder::session()?
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            Service {
+                sender,
+                config_path,
+            },
+        )?
+        .build()
+        .await?;
+
+    let _ = CONNECTION.set(connection);
+    Ok(())
+}
+
+/// Emits the `ScreenshotSaved` signal if the interface has been exported by this process.
+pub fn emit_screenshot_saved(path: &Path) {
+    let path = path.to_string_lossy().to_string();
+
+    emit(|ctxt| async move { Service::screenshot_saved(&ctxt, &path).await });
+}
+
+/// Emits the `ScreenshotCopied` signal if the interface has been exported by this process.
+pub fn emit_screenshot_copied() {
+    emit(|ctxt| async move { Service::screenshot_copied(&ctxt).await });
+}
+
+fn emit<F, Fut>(signal: F)
+where
+    F: FnOnce(SignalContext<'static>) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = zbus::Result<()>> + Send,
+{
+    let Some(connection) = CONNECTION.get() else {
+        return;
+    };
+
+    relm4::spawn(async move {
+        let result = match SignalContext::new(connection, OBJECT_PATH) {
+            Ok(ctxt) => signal(ctxt).await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            error!("Couldn't emit D-Bus signal: {}", err);
+        }
+    });
+}
+
+impl Service {
+    async fn capture(&self, target: CaptureTarget, path: String) -> fdo::Result<String> {
+        let path = PathBuf::from(path);
+        if path.is_relative() {
+            return Err(fdo::Error::InvalidArgs(
+                "The path has to be absolute.".to_string(),
+            ));
+        }
+
+        let config_path = self.config_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let options = Config::load_or_default(&config_path).export;
+
+            crate::capture_to_file(&target, &path, &options)
+                .map(|()| path.to_string_lossy().to_string())
+                .map_err(|err| fdo::Error::Failed(format!("{:#}", err)))
+        })
+        .await
+        .map_err(|err| fdo::Error::Failed(err.to_string()))?
+    }
+}
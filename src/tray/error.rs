@@ -3,6 +3,12 @@ pub enum Error {
     #[error(transparent)]
     IO(#[from] std::io::Error),
 
+    #[error("Received an invalid message: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("The daemon couldn't handle the request: {0}")]
+    Daemon(String),
+
     #[error("Couldn't acquire the socket: {0}")]
     AcquireSocket(rustix::io::Errno),
 
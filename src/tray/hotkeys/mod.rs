@@ -0,0 +1,142 @@
+//! Global hotkeys which are registered by the tray daemon.
+//!
+//! On X11 the keys are grabbed on the root window. On wayland there's no such mechanism, so the
+//! hotkeys are registered through the `GlobalShortcuts` interface of the xdg-desktop-portal.
+pub mod portal;
+pub mod x11;
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use relm4::Sender;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backend::CaptureTarget, frontend::window::main_window::Command};
+
+/// All actions which can be bound to a hotkey.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HotkeyAction {
+    /// Open the capture ui.
+    OpenEditor,
+
+    /// Copy a screenshot of all outputs into the clipboard.
+    CaptureScreen,
+
+    /// Copy a screenshot of the active window into the clipboard. (X11 only)
+    CaptureActiveWindow,
+}
+
+impl HotkeyAction {
+    pub fn command(&self) -> Command {
+        match self {
+            Self::OpenEditor => Command::Gui,
+            Self::CaptureScreen => Command::CaptureToClipboard(CaptureTarget::Screen),
+            Self::CaptureActiveWindow => Command::CaptureToClipboard(CaptureTarget::ActiveWindow),
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::OpenEditor => "Open the flakeshot editor",
+            Self::CaptureScreen => "Copy a screenshot of the whole screen",
+            Self::CaptureActiveWindow => "Copy a screenshot of the active window",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+/// A key combination like `Super+Shift+Print`.
+///
+/// The modifiers are case insensitive, the key is the name of its (X11) keysym.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: String,
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts
+            .pop()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| format!("The hotkey '{}' doesn't contain a key.", s))?;
+
+        let mut modifiers = Modifiers::default();
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "shift" => modifiers.shift = true,
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "super" | "logo" | "mod4" => modifiers.logo = true,
+                _ => return Err(format!("Unknown modifier '{}' in '{}'.", modifier, s)),
+            }
+        }
+
+        Ok(Self {
+            modifiers,
+            key: key.to_string(),
+        })
+    }
+}
+
+/// Registers the configured hotkeys. Invalid hotkeys are logged and skipped.
+pub async fn start(hotkeys: BTreeMap<String, HotkeyAction>, sender: Sender<Command>) {
+    let hotkeys: Vec<(Hotkey, HotkeyAction)> = hotkeys
+        .into_iter()
+        .filter_map(|(hotkey, action)| match hotkey.parse() {
+            Ok(hotkey) => Some((hotkey, action)),
+            Err(err) => {
+                error!("Ignoring hotkey: {}", err);
+                None
+            }
+        })
+        .collect();
+
+    if hotkeys.is_empty() {
+        return;
+    }
+
+    let result = if crate::backend::is_wayland() {
+        portal::register(hotkeys, sender).await
+    } else {
+        x11::register(hotkeys, sender)
+    };
+
+    if let Err(err) = result {
+        error!("Couldn't register the hotkeys: {:#}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hotkey, Modifiers};
+
+    #[test]
+    fn test_parse_hotkey() {
+        assert_eq!(
+            "Super+shift + Print".parse::<Hotkey>(),
+            Ok(Hotkey {
+                modifiers: Modifiers {
+                    shift: true,
+                    logo: true,
+                    ..Default::default()
+                },
+                key: "Print".to_string(),
+            })
+        );
+
+        assert!("Ctrl+".parse::<Hotkey>().is_err());
+        assert!("Hyper+a".parse::<Hotkey>().is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Registers the hotkeys through the `GlobalShortcuts` interface of the xdg-desktop-portal.
+//!
+//! See <https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.GlobalShortcuts.html>
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use futures_util::StreamExt;
+use relm4::Sender;
+use zbus::{
+    zvariant::{DynamicType, ObjectPath, OwnedObjectPath, OwnedValue, Value},
+    Connection, Proxy,
+};
+
+use crate::frontend::window::main_window::Command;
+
+use super::{Hotkey, HotkeyAction};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const GLOBAL_SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// Binds the hotkeys and sends the command of the action whenever one of them is activated.
+///
+/// The hotkeys are only *suggestions*, the desktop environment might ask the user to confirm
+/// or to change them. This function only returns if registering failed or the portal closed
+/// the session.
+pub async fn register(
+    hotkeys: Vec<(Hotkey, HotkeyAction)>,
+    sender: Sender<Command>,
+) -> anyhow::Result<()> {
+    let conn = Connection::session().await?;
+    let proxy = Proxy::new(
+        &conn,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        GLOBAL_SHORTCUTS_INTERFACE,
+    )
+    .await?;
+
+    // subscribe before binding so we don't miss an activation
+    let mut activations = proxy.receive_signal("Activated").await?;
+
+    let session = create_session(&conn, &proxy).await?;
+
+    let shortcuts: Vec<(String, HashMap<&str, Value>)> = hotkeys
+        .iter()
+        .map(|(hotkey, action)| {
+            let options = HashMap::from([
+                ("description", Value::from(action.description())),
+                ("preferred_trigger", Value::from(get_trigger(hotkey))),
+            ]);
+
+            // the trigger is unique, so it doubles as the id of the shortcut
+            (get_trigger(hotkey), options)
+        })
+        .collect();
+
+    let token = "flakeshot_bind_shortcuts";
+    let options = HashMap::from([("handle_token", Value::from(token))]);
+    request(
+        &conn,
+        &proxy,
+        token,
+        "BindShortcuts",
+        &(&session, shortcuts, "", options),
+    )
+    .await
+    .context("Couldn't bind the shortcuts")?;
+
+    while let Some(message) = activations.next().await {
+        let (_session, id, _timestamp, _options): (
+            OwnedObjectPath,
+            String,
+            u64,
+            HashMap<String, OwnedValue>,
+        ) = message.body()?;
+
+        let action = hotkeys
+            .iter()
+            .find(|(hotkey, _)| get_trigger(hotkey) == id)
+            .map(|(_, action)| action);
+
+        if let Some(action) = action {
+            sender
+                .send(action.command())
+                .map_err(|_| anyhow!("The daemon is shutting down."))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_session(conn: &Connection, proxy: &Proxy<'_>) -> anyhow::Result<OwnedObjectPath> {
+    let token = "flakeshot_create_session";
+    let options = HashMap::from([
+        ("handle_token", Value::from(token)),
+        ("session_handle_token", Value::from("flakeshot")),
+    ]);
+
+    let results = request(conn, proxy, token, "CreateSession", &(options,))
+        .await
+        .context("Couldn't create a global shortcuts session")?;
+
+    // the specification says it's a string but some implementations return an object path
+    let session = results.get("session_handle").and_then(|handle| {
+        handle
+            .downcast_ref::<str>()
+            .and_then(|handle| ObjectPath::try_from(handle).ok())
+            .or_else(|| handle.downcast_ref::<ObjectPath>().cloned())
+    });
+
+    session
+        .map(OwnedObjectPath::from)
+        .ok_or_else(|| anyhow!("The portal didn't return a session handle."))
+}
+
+/// Calls a method of the portal which answers through the `Response` signal of a request object.
+async fn request<B>(
+    conn: &Connection,
+    proxy: &Proxy<'_>,
+    token: &str,
+    method: &str,
+    body: &B,
+) -> anyhow::Result<HashMap<String, OwnedValue>>
+where
+    B: serde::Serialize + DynamicType,
+{
+    let sender = conn
+        .unique_name()
+        .ok_or_else(|| anyhow!("The D-Bus connection doesn't have a name."))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+
+    let request = Proxy::new(conn, PORTAL_DESTINATION, request_path, REQUEST_INTERFACE).await?;
+    let mut responses = request.receive_signal("Response").await?;
+
+    proxy.call_method(method, body).await?;
+
+    let response = responses
+        .next()
+        .await
+        .ok_or_else(|| anyhow!("The portal didn't answer."))?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = response.body()?;
+
+    match code {
+        0 => Ok(results),
+        1 => Err(anyhow!("The request has been cancelled by the user.")),
+        _ => Err(anyhow!("The request failed.")),
+    }
+}
+
+/// Converts the hotkey into the format of the
+/// [shortcuts specification](https://specifications.freedesktop.org/shortcuts-spec/latest/).
+fn get_trigger(hotkey: &Hotkey) -> String {
+    let modifiers = &hotkey.modifiers;
+
+    let mut trigger = String::new();
+    for (enabled, name) in [
+        (modifiers.ctrl, "CTRL+"),
+        (modifiers.alt, "ALT+"),
+        (modifiers.shift, "SHIFT+"),
+        (modifiers.logo, "LOGO+"),
+    ] {
+        if enabled {
+            trigger.push_str(name);
+        }
+    }
+
+    trigger.push_str(&hotkey.key);
+    trigger
+}
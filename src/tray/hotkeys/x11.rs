@@ -0,0 +1,191 @@
+//! Grabs the hotkeys on the root window of the xorg-server.
+use anyhow::{anyhow, Context};
+use relm4::Sender;
+use tracing::error;
+use x11rb::{
+    connection::Connection,
+    errors::ReplyError,
+    protocol::{
+        xproto::{ConnectionExt, GrabMode, Keycode, ModMask, Window},
+        Event,
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::frontend::window::main_window::Command;
+
+use super::{Hotkey, HotkeyAction, Modifiers};
+
+/// Shift, Control, Mod1 (Alt) and Mod4 (Super).
+/// Everything else (Caps Lock, Num Lock, ...) is ignored when a key is pressed.
+const RELEVANT_MODIFIERS: u16 = 1 | 4 | 8 | 64;
+
+/// Grabs the hotkeys and sends the command of the action whenever one of them is pressed.
+pub fn register(
+    hotkeys: Vec<(Hotkey, HotkeyAction)>,
+    sender: Sender<Command>,
+) -> anyhow::Result<()> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let mut grabbed: Vec<(Keycode, u16, HotkeyAction)> = vec![];
+    for (hotkey, action) in hotkeys {
+        match grab(&conn, root, &hotkey) {
+            Ok((keycode, modifiers)) => grabbed.push((keycode, modifiers, action)),
+            Err(err) => error!("Ignoring hotkey: {:#}", err),
+        }
+    }
+
+    std::thread::spawn(move || {
+        if let Err(err) = handle_events(&conn, &grabbed, &sender) {
+            error!("Stopped listening for hotkeys: {}", err);
+        }
+    });
+
+    Ok(())
+}
+
+/// Grabs the key of the hotkey and returns its keycode and modifiers.
+fn grab(conn: &RustConnection, root: Window, hotkey: &Hotkey) -> anyhow::Result<(Keycode, u16)> {
+    let keycode = get_keycode(conn, &hotkey.key)?
+        .ok_or_else(|| anyhow!("The key '{}' doesn't exist on your keyboard.", hotkey.key))?;
+    let modifiers = get_mod_mask(hotkey.modifiers);
+
+    // the grab only matches the exact modifiers, so we have to grab the key for each
+    // combination of the lock modifiers as well
+    let locks = [
+        ModMask::from(0u16),
+        ModMask::LOCK,
+        ModMask::M2,
+        ModMask::LOCK | ModMask::M2,
+    ];
+
+    for lock in locks {
+        let result = conn
+            .grab_key(
+                false,
+                root,
+                modifiers | lock,
+                keycode,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.check());
+
+        if let Err(err) = result {
+            // a half grabbed hotkey would swallow the key without doing anything
+            for lock in locks {
+                let _ = conn.ungrab_key(keycode, root, modifiers | lock);
+            }
+
+            return Err(err)
+                .with_context(|| format!("Couldn't grab '{}'. Is it already in use?", hotkey.key));
+        }
+    }
+
+    Ok((keycode, u16::from(modifiers)))
+}
+
+fn handle_events(
+    conn: &RustConnection,
+    grabbed: &[(Keycode, u16, HotkeyAction)],
+    sender: &Sender<Command>,
+) -> anyhow::Result<()> {
+    loop {
+        if let Event::KeyPress(event) = conn.wait_for_event()? {
+            let state = u16::from(event.state) & RELEVANT_MODIFIERS;
+
+            let action = grabbed
+                .iter()
+                .find(|(keycode, modifiers, _)| *keycode == event.detail && *modifiers == state)
+                .map(|(_, _, action)| action);
+
+            if let Some(action) = action {
+                sender
+                    .send(action.command())
+                    .map_err(|_| anyhow!("The daemon is shutting down."))?;
+            }
+        }
+    }
+}
+
+fn get_mod_mask(modifiers: Modifiers) -> ModMask {
+    let mut mask = ModMask::from(0u16);
+
+    if modifiers.shift {
+        mask = mask | ModMask::SHIFT;
+    }
+    if modifiers.ctrl {
+        mask = mask | ModMask::CONTROL;
+    }
+    if modifiers.alt {
+        mask = mask | ModMask::M1;
+    }
+    if modifiers.logo {
+        mask = mask | ModMask::M4;
+    }
+
+    mask
+}
+
+/// Returns the keycode which produces the keysym with the given name.
+fn get_keycode(conn: &RustConnection, key: &str) -> anyhow::Result<Option<Keycode>> {
+    let keysym = get_keysym(key).ok_or_else(|| anyhow!("Unknown key '{}'.", key))?;
+
+    let setup = conn.setup();
+    let min_keycode = setup.min_keycode;
+    let count = setup.max_keycode - min_keycode + 1;
+
+    let mapping = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+    let keysyms_per_keycode = usize::from(mapping.keysyms_per_keycode).max(1);
+
+    Ok(mapping
+        .keysyms
+        .chunks(keysyms_per_keycode)
+        .position(|keysyms| keysyms.contains(&keysym))
+        .map(|index| min_keycode + index as Keycode))
+}
+
+/// Maps the name of a key to its keysym (see `X11/keysymdef.h`).
+fn get_keysym(key: &str) -> Option<u32> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphanumeric() {
+            // the keysyms of latin letters and digits are their (lowercase) ascii values
+            return Some(u32::from(c.to_ascii_lowercase()));
+        }
+    }
+
+    if let Some(n) = key
+        .strip_prefix('F')
+        .and_then(|n| n.parse::<u32>().ok())
+        .filter(|n| (1..=24).contains(n))
+    {
+        return Some(0xffbe + n - 1);
+    }
+
+    let keysym = match key.to_lowercase().as_str() {
+        "print" => 0xff61,
+        "escape" => 0xff1b,
+        "space" => 0x0020,
+        "return" | "enter" => 0xff0d,
+        "tab" => 0xff09,
+        "backspace" => 0xff08,
+        "insert" => 0xff63,
+        "delete" => 0xffff,
+        "home" => 0xff50,
+        "end" => 0xff57,
+        "page_up" | "pageup" => 0xff55,
+        "page_down" | "pagedown" => 0xff56,
+        "left" => 0xff51,
+        "up" => 0xff52,
+        "right" => 0xff53,
+        "down" => 0xff54,
+        "pause" => 0xff13,
+        "scroll_lock" => 0xff14,
+        _ => return None,
+    };
+
+    Some(keysym)
+}
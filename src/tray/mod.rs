@@ -1,22 +1,85 @@
+pub mod control;
+pub mod dbus;
 pub mod error;
+pub mod hotkeys;
 
-use std::{fs::File, io::Cursor};
+use std::{
+    collections::BTreeSet, fs::File, io::Cursor, path::PathBuf, sync::OnceLock, time::Duration,
+};
 
 use anyhow::Context;
 use image::{ImageBuffer, Rgba};
 use ksni;
 use relm4::Sender;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::error;
 
-use crate::{frontend::window::main_window::Command, get_xdg};
+use crate::{
+    backend::{self, CaptureTarget},
+    cli::EditArgs,
+    config::Config,
+    frontend::window::main_window::Command,
+    get_xdg, history, recent,
+};
 
 use self::error::Error;
 
 const LOCK_FILENAME: &str = "flakeshot.lock";
 
+/// How often the daemon removes old screenshots from the history.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The delays which are offered by "Capture with delay".
+const CAPTURE_DELAYS: [u64; 3] = [3, 5, 10];
+
+/// Sends updates to the tray. It's only set if the tray is running in this process.
+static UPDATE_SENDER: OnceLock<UnboundedSender<Update>> = OnceLock::new();
+
+/// Something which takes a while and is shown by the icon and tooltip of the tray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Activity {
+    Capturing,
+    Recording,
+    Timelapse,
+    Uploading,
+}
+
+impl Activity {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Capturing => "Capturing",
+            Self::Recording => "Recording",
+            Self::Timelapse => "Timelapse running",
+            Self::Uploading => "Uploading",
+        }
+    }
+
+    fn icon_name(&self) -> &'static str {
+        match self {
+            Self::Capturing => "camera-photo",
+            Self::Recording => "media-record",
+            Self::Timelapse => "appointment-soon",
+            Self::Uploading => "network-transmit",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Activity(Activity, bool),
+    Refresh,
+}
+
 #[derive(Debug)]
 struct Tray {
     icon: ksni::Icon,
     sender: Sender<Command>,
+    activities: BTreeSet<Activity>,
+
+    /// The names of the outputs and the recently saved screenshots. They are only reloaded on
+    /// [`refresh`] since the menu is rebuilt very often.
+    outputs: Vec<String>,
+    recent: Vec<PathBuf>,
 }
 
 impl Tray {
@@ -29,14 +92,93 @@ impl Tray {
         // rgba => argb
         data.chunks_mut(4).for_each(|pixel| pixel.rotate_right(1));
 
-        Self {
+        let mut tray = Self {
             icon: ksni::Icon {
                 width: width as i32,
                 height: height as i32,
                 data,
             },
             sender,
+            activities: BTreeSet::new(),
+            outputs: vec![],
+            recent: vec![],
+        };
+        tray.reload();
+
+        tray
+    }
+
+    /// Loads the outputs and the recently saved screenshots again.
+    fn reload(&mut self) {
+        self.outputs = backend::get_output_names().unwrap_or_else(|err| {
+            error!("Couldn't list the outputs: {}", err);
+            vec![]
+        });
+        self.recent = recent::load();
+    }
+
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Activity(activity, true) => {
+                self.activities.insert(activity);
+            }
+            Update::Activity(activity, false) => {
+                self.activities.remove(&activity);
+            }
+            // the menu is rebuilt on every update
+            Update::Refresh => self.reload(),
+        }
+    }
+
+    /// Creates a menu entry which sends the command when it's clicked.
+    fn command_item(&self, label: impl ToString, command: Command) -> ksni::MenuItem<Self> {
+        let sender = self.sender.clone();
+
+        ksni::menu::StandardItem {
+            label: label.to_string(),
+            activate: Box::new(move |_| {
+                sender
+                    .send(command.clone())
+                    .expect("Couldn't send tray command")
+            }),
+            ..Default::default()
         }
+        .into()
+    }
+
+    fn output_items(&self) -> Vec<ksni::MenuItem<Self>> {
+        self.outputs
+            .iter()
+            .map(|name| {
+                self.command_item(
+                    name,
+                    Command::CaptureToClipboard(CaptureTarget::Output(name.clone())),
+                )
+            })
+            .collect()
+    }
+
+    fn recent_items(&self) -> Vec<ksni::MenuItem<Self>> {
+        self.recent
+            .iter()
+            .cloned()
+            .map(|path| {
+                let label = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+                ksni::menu::SubMenu {
+                    label,
+                    submenu: vec![
+                        open_item(path.clone()),
+                        self.command_item("Copy to clipboard", Command::CopyFileToClipboard(path)),
+                    ],
+                    ..Default::default()
+                }
+                .into()
+            })
+            .collect()
     }
 }
 
@@ -45,6 +187,29 @@ impl ksni::Tray for Tray {
         vec![self.icon.clone()]
     }
 
+    /// Overrides the pixmap while something is in progress.
+    fn icon_name(&self) -> String {
+        self.activities
+            .first()
+            .map(|activity| activity.icon_name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let description = self
+            .activities
+            .iter()
+            .map(|activity| activity.description())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        ksni::ToolTip {
+            title: "Flakeshot".into(),
+            description,
+            ..Default::default()
+        }
+    }
+
     fn id(&self) -> String {
         env!("CARGO_PKG_NAME").into()
     }
@@ -58,36 +223,170 @@ impl ksni::Tray for Tray {
     fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
         use ksni::menu::*;
 
-        let sender = self.sender.clone();
+        let delay_items = CAPTURE_DELAYS
+            .into_iter()
+            .map(|secs| {
+                self.command_item(
+                    format!("{} seconds", secs),
+                    Command::DelayedGui(Duration::from_secs(secs)),
+                )
+            })
+            .collect();
 
-        vec![StandardItem {
-            label: "Quit".into(),
-            activate: Box::new(move |_| {
-                sender
-                    .send(Command::Quit)
-                    .expect("Couldn't send quit command")
-            }),
-            ..Default::default()
-        }
-        .into()]
+        let recent_items = self.recent_items();
+
+        vec![
+            self.command_item("Open editor", Command::Gui),
+            self.command_item(
+                "Edit clipboard image",
+                Command::Edit(EditArgs {
+                    path: None,
+                    from_clipboard: true,
+                }),
+            ),
+            self.command_item(
+                "Capture full screen",
+                Command::CaptureToClipboard(CaptureTarget::Screen),
+            ),
+            SubMenu {
+                label: "Capture output".into(),
+                submenu: self.output_items(),
+                ..Default::default()
+            }
+            .into(),
+            SubMenu {
+                label: "Capture with delay".into(),
+                submenu: delay_items,
+                ..Default::default()
+            }
+            .into(),
+            SubMenu {
+                label: "Recent".into(),
+                enabled: !recent_items.is_empty(),
+                submenu: recent_items,
+                ..Default::default()
+            }
+            .into(),
+            self.command_item("History", Command::OpenHistory),
+            CheckmarkItem {
+                label: "Incognito (skip history once)".into(),
+                checked: history::is_incognito(),
+                activate: Box::new(|_| history::set_incognito(!history::is_incognito())),
+                ..Default::default()
+            }
+            .into(),
+            MenuItem::Separator,
+            self.command_item("Stop recording", Command::StopRecording),
+            self.command_item("Start/stop timelapse", Command::ToggleTimelapse),
+            MenuItem::Separator,
+            self.command_item("Open config", Command::OpenConfig),
+            self.command_item("Reload config", Command::ReloadConfig),
+            MenuItem::Separator,
+            self.command_item("Quit", Command::Quit),
+        ]
     }
 }
 
-pub async fn start(sender: Sender<Command>) {
+fn open_item(path: PathBuf) -> ksni::MenuItem<Tray> {
+    ksni::menu::StandardItem {
+        label: "Open".into(),
+        activate: Box::new(move |_| {
+            if let Err(err) = crate::open_with_default_app(&path) {
+                error!("{:#}", err);
+            }
+        }),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Shows in the tray that the activity started (`active == true`) or stopped.
+///
+/// Does nothing if the tray isn't running in this process.
+pub fn set_activity(activity: Activity, active: bool) {
+    send_update(Update::Activity(activity, active));
+}
+
+/// Reloads the outputs and the recent screenshots and rebuilds the menu of the tray, for
+/// example after a screenshot has been saved.
+///
+/// Does nothing if the tray isn't running in this process.
+pub fn refresh() {
+    send_update(Update::Refresh);
+}
+
+/// Returns `true` if the tray (and therefore the daemon) is running in this process.
+pub fn is_running() -> bool {
+    UPDATE_SENDER.get().is_some()
+}
+
+fn send_update(update: Update) {
+    if let Some(sender) = UPDATE_SENDER.get() {
+        let _ = sender.send(update);
+    }
+}
+
+pub async fn start(sender: Sender<Command>, config_path: PathBuf) {
     let _lock_file = match acquire_lock() {
         Ok(Some(lock_file)) => lock_file,
-        Ok(None) => return,
+        Ok(None) => {
+            error!("{}", Error::AlreadyRunning);
+            return;
+        }
         Err(e) => {
             panic!("Couldn't start the tray: {}", e);
         }
     };
 
-    let (_tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let listener = control::bind().expect("Couldn't create the control socket");
+    tokio::spawn(control::serve(listener, sender.clone()));
+
+    if let Err(err) = dbus::start(sender.clone(), config_path.clone()).await {
+        error!("Couldn't export the D-Bus interface: {}", err);
+    }
+
+    let hotkeys = Config::load_or_default(&config_path).hotkeys;
+    tokio::spawn(hotkeys::start(hotkeys, sender.clone()));
+    tokio::spawn(prune_history_periodically(sender.clone()));
+
+    let (update_tx, update_rx) = mpsc::unbounded_channel();
+    let _ = UPDATE_SENDER.set(update_tx);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(forward_updates(update_rx, tx));
+
     ksni::run_async(Tray::new(sender), rx)
         .await
         .expect("Couldn't run tray");
 }
 
+async fn prune_history_periodically(sender: Sender<Command>) {
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if sender.send(Command::PruneHistory).is_err() {
+            return;
+        }
+    }
+}
+
+/// Applies our updates to the tray which lets ksni refresh the properties and the menu.
+async fn forward_updates(
+    mut updates: UnboundedReceiver<Update>,
+    tray: UnboundedSender<Box<dyn FnOnce(&mut Tray) + Send>>,
+) {
+    while let Some(update) = updates.recv().await {
+        if tray
+            .send(Box::new(move |tray: &mut Tray| tray.update(update)))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
 pub fn acquire_lock() -> anyhow::Result<Option<File>> {
     let lock_file_path = get_xdg().place_runtime_file(LOCK_FILENAME).unwrap();
 
@@ -0,0 +1,264 @@
+//! Uploads screenshots to the HTTP endpoints of the config.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use image::DynamicImage;
+use regex::Regex;
+
+use crate::{
+    config::{self, Uploader},
+    export,
+};
+
+/// The maximum time an upload may take.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Uploads the image in the export format of the config.
+///
+/// # Return value
+/// The URL of the uploaded image.
+pub fn upload_image(
+    uploader: &Uploader,
+    image: &DynamicImage,
+    options: &config::Export,
+) -> anyhow::Result<String> {
+    let format = options.format;
+    let data = export::encode_to_vec(image, format, options)?;
+    let filename = format!("screenshot.{}", format.extension());
+
+    upload(uploader, &data, format.mime_type(), &filename)
+}
+
+/// Sends the data to the endpoint of the uploader and extracts the URL from the response.
+pub fn upload(
+    uploader: &Uploader,
+    data: &[u8],
+    mime_type: &str,
+    filename: &str,
+) -> anyhow::Result<String> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let mut request = agent.request(&uploader.method, &uploader.url);
+    for (name, value) in &uploader.headers {
+        request = request.set(name, value);
+    }
+
+    let response = match &uploader.file_field {
+        Some(field) => {
+            let boundary = get_boundary();
+            let body = multipart_body(&boundary, &uploader.form, field, filename, mime_type, data);
+
+            request
+                .set(
+                    "Content-Type",
+                    &format!("multipart/form-data; boundary={}", boundary),
+                )
+                .send_bytes(&body)
+        }
+        None if !uploader.form.is_empty() => {
+            bail!("Form fields can only be sent if `file_field` is set.")
+        }
+        None => request.set("Content-Type", mime_type).send_bytes(data),
+    };
+
+    let body = match response {
+        Ok(response) => response
+            .into_string()
+            .context("Couldn't read the response of the upload")?,
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("The server answered with {}: {}", code, body.trim());
+        }
+        Err(err) => return Err(anyhow!(err).context("Couldn't upload the screenshot")),
+    };
+
+    extract_url(uploader, &body)
+}
+
+/// Finds the URL in the response with the JSON pointer or regex of the uploader.
+fn extract_url(uploader: &Uploader, body: &str) -> anyhow::Result<String> {
+    let url = if let Some(pointer) = &uploader.json_pointer {
+        let json: serde_json::Value =
+            serde_json::from_str(body).context("The response isn't valid JSON")?;
+
+        json.pointer(pointer)
+            .and_then(|value| value.as_str())
+            .with_context(|| format!("The response doesn't contain a string at '{}'", pointer))?
+            .to_string()
+    } else if let Some(regex) = &uploader.regex {
+        let regex = Regex::new(regex).context("The regex of the uploader is invalid")?;
+        let captures = regex
+            .captures(body)
+            .with_context(|| format!("The response doesn't match '{}'", regex))?;
+
+        captures
+            .get(1)
+            .or_else(|| captures.get(0))
+            .map(|url| url.as_str().to_string())
+            .unwrap_or_default()
+    } else {
+        body.trim().to_string()
+    };
+
+    if url.is_empty() {
+        bail!("The response doesn't contain a URL.");
+    }
+
+    Ok(url)
+}
+
+fn get_boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+
+    format!("flakeshot-{:x}-{:x}", std::process::id(), nanos)
+}
+
+/// Creates a `multipart/form-data` body (RFC 7578) with the form fields and the file.
+fn multipart_body<'a>(
+    boundary: &str,
+    form: impl IntoIterator<Item = (&'a String, &'a String)>,
+    field: &str,
+    filename: &str,
+    mime_type: &str,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 1024);
+
+    for (name, value) in form {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary, field, filename, mime_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    use super::*;
+
+    /// Answers a single request with the response and returns the received request.
+    fn stub_server(response: &'static str) -> (String, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/upload", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                request.extend_from_slice(line.as_bytes());
+
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.extend_from_slice(&body);
+
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+
+            request
+        });
+
+        (url, server)
+    }
+
+    fn uploader(url: String) -> Uploader {
+        Uploader {
+            method: "POST".to_string(),
+            url,
+            file_field: None,
+            headers: BTreeMap::new(),
+            form: BTreeMap::new(),
+            json_pointer: None,
+            regex: None,
+        }
+    }
+
+    #[test]
+    fn test_multipart_upload_extracts_url_with_json_pointer() {
+        let (url, server) = stub_server(r#"{"data": {"link": "https://i.example.com/a.png"}}"#);
+        let uploader = Uploader {
+            file_field: Some("image".to_string()),
+            headers: BTreeMap::from([("Authorization".to_string(), "Token secret".to_string())]),
+            form: BTreeMap::from([("album".to_string(), "screenshots".to_string())]),
+            json_pointer: Some("/data/link".to_string()),
+            ..uploader(url)
+        };
+
+        let url = upload(&uploader, b"PNGDATA", "image/png", "screenshot.png").unwrap();
+        assert_eq!(url, "https://i.example.com/a.png");
+
+        let request = String::from_utf8(server.join().unwrap()).unwrap();
+        assert!(request.starts_with("POST /upload "));
+        assert!(request.contains("Authorization: Token secret\r\n"));
+        assert!(request.contains("name=\"album\"\r\n\r\nscreenshots\r\n"));
+        assert!(request.contains(
+            "name=\"image\"; filename=\"screenshot.png\"\r\nContent-Type: image/png\r\n\r\nPNGDATA\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_raw_upload_extracts_url_with_regex() {
+        let (url, server) = stub_server("<a href=\"https://i.example.com/b.png\">link</a>");
+        let uploader = Uploader {
+            method: "PUT".to_string(),
+            regex: Some("href=\"([^\"]+)\"".to_string()),
+            ..uploader(url)
+        };
+
+        let url = upload(&uploader, b"PNGDATA", "image/png", "screenshot.png").unwrap();
+        assert_eq!(url, "https://i.example.com/b.png");
+
+        let request = String::from_utf8(server.join().unwrap()).unwrap();
+        assert!(request.starts_with("PUT /upload "));
+        assert!(request.ends_with("\r\n\r\nPNGDATA"));
+    }
+
+    #[test]
+    fn test_plain_response_is_used_as_url() {
+        let (url, _server) = stub_server("https://i.example.com/c.png\n");
+
+        let url = upload(&uploader(url), b"PNGDATA", "image/png", "screenshot.png").unwrap();
+        assert_eq!(url, "https://i.example.com/c.png");
+    }
+}